@@ -2,19 +2,561 @@ use serde::{Deserialize, Serialize};
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
 
+// box in subpixel floats, relative to the top-left of the image the caller passed in (x right, y down);
+// never rounded on the way back from the model's input, so scaling up for display doesn't drift
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct Det { pub x: f32, pub y: f32, pub w: f32, pub h: f32, pub label: String, pub score: f32 }
+pub struct Det {
+  pub x: f32, pub y: f32, pub w: f32, pub h: f32, pub label: String, pub score: f32,
+  // leader-linked text naming the member, e.g. "2x4"; see attach_callouts
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub callout: Option<String>,
+}
+
+// how an image was fitted into the model's square input: uniform scale, then centered padding
+#[derive(Clone, Copy, Debug)]
+pub struct Letterbox { pub scale: f32, pub pad_x: f32, pub pad_y: f32 }
+
+impl Letterbox {
+  // image pixels → input pixels
+  pub fn fit(w: u32, h: u32, size: u32) -> Self {
+    let scale = size as f32 / w.max(h).max(1) as f32;
+    Letterbox { scale, pad_x: (size as f32 - w as f32 * scale) * 0.5, pad_y: (size as f32 - h as f32 * scale) * 0.5 }
+  }
+
+  // a box in input pixels back to image pixels, kept fractional
+  pub fn to_source(&self, d: Det) -> Det {
+    Det { x: (d.x - self.pad_x) / self.scale, y: (d.y - self.pad_y) / self.scale, w: d.w / self.scale, h: d.h / self.scale, ..d }
+  }
+}
+
+pub const DEFAULT_SCALES: &[f32] = &[1.0, 0.66];
+const NMS_IOU: f32 = 0.5;
+
+fn infer(model_path: &std::path::Path, img: &image::DynamicImage) -> Result<Vec<Det>, String> {
+  // TODO: Initialize ONNX Runtime DirectML session and run inference; letterbox `img` to the input
+  // size with Letterbox::fit and return boxes through Letterbox::to_source, i.e. in `img` pixels
+  let _ = (model_path, img);
+  Ok(vec![])
+}
+
+pub fn iou(a: &Det, b: &Det) -> f32 {
+  let ix = ((a.x + a.w).min(b.x + b.w) - a.x.max(b.x)).max(0.0);
+  let iy = ((a.y + a.h).min(b.y + b.h) - a.y.max(b.y)).max(0.0);
+  let inter = ix * iy;
+  let union = a.w * a.h + b.w * b.h - inter;
+  if union <= 0.0 { 0.0 } else { inter / union }
+}
+
+// greedy per-label NMS, highest score first
+pub fn nms(mut dets: Vec<Det>, iou_thr: f32) -> Vec<Det> {
+  dets.sort_by(|a, b| b.score.total_cmp(&a.score));
+  let mut keep: Vec<Det> = Vec::new();
+  for d in dets {
+    if keep.iter().all(|k| k.label != d.label || iou(k, &d) < iou_thr) { keep.push(d); }
+  }
+  keep
+}
+
+// detector knobs; each model keeps its own in settings, see params_for
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct DetectParams {
+  pub score_threshold: f32,
+  pub iou: f32,
+  // pyramid scales the page is run at, each in (0, MAX_PYRAMID_SCALE]
+  pub scales: Vec<f32>,
+  // side of the square tiles each scaled image is cut into, in pixels; 0 runs the image whole
+  pub tile: u32,
+  // pixels neighbouring tiles share, so a symbol on a seam is whole in one of them
+  pub tile_overlap: u32,
+}
+
+impl Default for DetectParams {
+  fn default() -> Self { DetectParams { score_threshold: 0.25, iou: NMS_IOU, scales: DEFAULT_SCALES.to_vec(), tile: 0, tile_overlap: 64 } }
+}
+
+pub const MAX_PYRAMID_SCALE: f32 = 4.0;
+const TILE_RANGE: std::ops::RangeInclusive<u32> = 64..=8192;
+
+// tile starts along one axis of `len` pixels: every `tile - overlap`, the last one flush with the end
+fn tile_origins(len: u32, tile: u32, overlap: u32) -> Vec<u32> {
+  if tile == 0 || len <= tile { return vec![0]; }
+  let step = (tile - overlap).max(1);
+  let mut v: Vec<u32> = (0..).map(|i| i * step).take_while(|&o| o + tile < len).collect();
+  v.push(len - tile);
+  v
+}
+
+// the model over `img` tile by tile (see DetectParams::tile), boxes in `img` pixels
+fn infer_tiled(model_path: &std::path::Path, img: &image::DynamicImage, params: &DetectParams) -> Result<Vec<Det>, String> {
+  if params.tile == 0 || (img.width() <= params.tile && img.height() <= params.tile) { return infer(model_path, img); }
+  let mut out = Vec::new();
+  for &ty in &tile_origins(img.height(), params.tile, params.tile_overlap) {
+    for &tx in &tile_origins(img.width(), params.tile, params.tile_overlap) {
+      let crop = img.crop_imm(tx, ty, params.tile.min(img.width() - tx), params.tile.min(img.height() - ty));
+      out.extend(infer(model_path, &crop)?.into_iter().map(|d| Det { x: d.x + tx as f32, y: d.y + ty as f32, ..d }));
+    }
+  }
+  Ok(out)
+}
+
+// run the detector at each scale, map boxes back to source pixels, drop low scores and merge overlaps
+pub fn detect_pyramid(model_path: &std::path::Path, img: &image::DynamicImage, params: &DetectParams) -> Result<Vec<Det>, String> {
+  let mut all = Vec::new();
+  for &s in &params.scales {
+    if s.is_nan() || s <= 0.0 { return Err(format!("invalid pyramid scale {s}")); }
+    // the resized image has whole pixels, so map back by the factor actually applied on each axis, not `s`
+    let (dets, sx, sy) = if (s - 1.0).abs() < 1e-6 {
+      (infer_tiled(model_path, img, params)?, 1.0, 1.0)
+    } else {
+      let (w, h) = (((img.width() as f32) * s).round().max(1.0) as u32, ((img.height() as f32) * s).round().max(1.0) as u32);
+      let dets = infer_tiled(model_path, &img.resize_exact(w, h, image::imageops::FilterType::Triangle), params)?;
+      (dets, w as f32 / img.width().max(1) as f32, h as f32 / img.height().max(1) as f32)
+    };
+    all.extend(dets.into_iter().filter(|d| d.score >= params.score_threshold).map(|d| Det { x: d.x / sx, y: d.y / sy, w: d.w / sx, h: d.h / sy, ..d }));
+  }
+  Ok(nms(all, params.iou))
+}
+
+// execution providers the detector was built with, preferred first
+pub fn execution_providers() -> Vec<&'static str> {
+  if cfg!(target_os = "windows") { vec!["DirectML", "CPU"] } else { vec!["CPU"] }
+}
+
+// whether the model at `path` can be loaded into a session; false while infer is a stub
+pub fn model_loads(path: &std::path::Path) -> bool {
+  // TODO: build the ONNX Runtime session here once infer has one
+  let _ = path;
+  false
+}
+
+pub fn default_model_path() -> std::path::PathBuf {
+  std::path::Path::new("src-tauri").join("models").join("symbols.onnx")
+}
 
+// the model picked in settings, else the bundled one
+pub fn model_path() -> std::path::PathBuf {
+  crate::settings::get().model_path.map(std::path::PathBuf::from).unwrap_or_else(default_model_path)
+}
+
+// the model's saved params with any per-call overrides on top
+pub fn params_for(model: &std::path::Path, scales: Option<Vec<f32>>, score_threshold: Option<f32>, iou: Option<f32>) -> DetectParams {
+  let mut p = crate::settings::get().model_params.get(&*model.to_string_lossy()).cloned().unwrap_or_default();
+  if let Some(s) = scales { p.scales = s; }
+  if let Some(t) = score_threshold { p.score_threshold = t; }
+  if let Some(i) = iou { p.iou = i; }
+  p
+}
+
+fn check_params(p: &DetectParams) -> Result<(), String> {
+  if !(0.0..=1.0).contains(&p.score_threshold) { return Err(format!("score_threshold must be in [0,1], got {}", p.score_threshold)); }
+  if !(p.iou > 0.0 && p.iou <= 1.0) { return Err(format!("iou must be in (0,1], got {}", p.iou)); }
+  if p.scales.is_empty() { return Err("at least one scale is needed".into()); }
+  if let Some(s) = p.scales.iter().find(|s| !s.is_finite() || **s <= 0.0 || **s > MAX_PYRAMID_SCALE) {
+    return Err(format!("scales must be in (0,{MAX_PYRAMID_SCALE}], got {s}"));
+  }
+  if p.tile != 0 && !TILE_RANGE.contains(&p.tile) { return Err(format!("tile must be 0 or in {}..={}, got {}", TILE_RANGE.start(), TILE_RANGE.end(), p.tile)); }
+  if p.tile != 0 && p.tile_overlap >= p.tile / 2 { return Err(format!("tile_overlap must be under half the tile, got {} for {}", p.tile_overlap, p.tile)); }
+  Ok(())
+}
+
+// makes `model_path` the active model; its saved params apply from then on
+#[tauri::command]
+pub fn select_model(model_path: String) -> Result<DetectParams, String> {
+  if !std::path::Path::new(&model_path).exists() { return Err(format!("{model_path}: no such model")); }
+  crate::settings::update(|s| s.model_path = Some(model_path.clone()))?;
+  Ok(params_for(std::path::Path::new(&model_path), None, None, None))
+}
+
+#[tauri::command]
+pub fn set_model_defaults(model_path: String, params: DetectParams) -> Result<(), String> {
+  check_params(&params)?;
+  crate::settings::update(|s| { s.model_params.insert(model_path, params); }).map(|_| ())
+}
+
+#[tauri::command]
+pub fn reset_model_defaults(model_path: String) -> Result<DetectParams, String> {
+  crate::settings::update(|s| { s.model_params.remove(&model_path); })?;
+  Ok(DetectParams::default())
+}
+
+// PNG, JPEG or WebP by their magic bytes, whatever the caller called it
+pub fn decode_image(bytes: &[u8]) -> Result<image::DynamicImage, String> {
+  use image::ImageFormat;
+  match image::guess_format(bytes) {
+    Ok(f @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => image::load_from_memory_with_format(bytes, f).map_err(|e| e.to_string()),
+    Ok(f) => Err(format!("unsupported_image_format: {f:?}; send PNG, JPEG or WebP")),
+    Err(_) => Err("unsupported_image_format: not a recognised image; send PNG, JPEG or WebP".into()),
+  }
+}
+
+// Ways in, by how the pixels reach Rust:
+//  - detect_symbols: base64 PNG, JPEG or WebP over IPC. Fine for small crops; costs ~33% extra bytes
+//    plus encode/decode, less with JPEG for photo-sourced drawings.
+//  - detect_region: the same, for a tile or padded crop, with boxes mapped back into the full image.
+//  - detect_symbols_file: an image the frontend already wrote to disk (e.g. a canvas export).
+//  - detect_symbols_page: Rust renders the page itself, nothing crosses the bridge. Prefer this for whole
+//    pages and large regions.
 #[tauri::command]
-pub async fn detect_symbols(image_png_base64: String) -> Result<Vec<Det>, String> {
+pub async fn detect_symbols(image_png_base64: String, scales: Option<Vec<f32>>, score_threshold: Option<f32>, iou: Option<f32>) -> Result<Vec<Det>, String> {
   // In this first pass, return a stub if model is missing; keep shape stable
-  let _bytes = BASE64.decode(image_png_base64).map_err(|e| e.to_string())?;
-  let model_path = std::path::Path::new("src-tauri").join("models").join("symbols.onnx");
+  let bytes = BASE64.decode(image_png_base64).map_err(|e| e.to_string())?;
+  // decoded first so a bad format is reported even without a model
+  let img = decode_image(&bytes)?;
+  let model_path = model_path();
   if !model_path.exists() {
     return Ok(vec![]);
   }
-  // TODO: Initialize ONNX Runtime DirectML session and run inference
-  Ok(vec![])
+  let params = params_for(&model_path, scales, score_threshold, iou);
+  check_params(&params)?;
+  detect_pyramid(&model_path, &img, &params)
+}
+
+#[tauri::command]
+pub async fn detect_symbols_file(image_path: String, scales: Option<Vec<f32>>, score_threshold: Option<f32>, iou: Option<f32>) -> Result<Vec<Det>, String> {
+  let model_path = model_path();
+  if !model_path.exists() { return Ok(vec![]); }
+  let params = params_for(&model_path, scales, score_threshold, iou);
+  check_params(&params)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let bytes = std::fs::read(&image_path).map_err(|e| format!("{image_path}: {e}"))?;
+    let img = decode_image(&bytes).map_err(|e| format!("{image_path}: {e}"))?;
+    detect_pyramid(&model_path, &img, &params)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// boxes are in pixels of the page rendered at `dpi`
+#[tauri::command]
+pub async fn detect_symbols_page(pdf_path: String, page: u32, dpi: Option<u32>, scales: Option<Vec<f32>>, score_threshold: Option<f32>, iou: Option<f32>) -> Result<Vec<Det>, String> {
+  let model_path = model_path();
+  if !model_path.exists() { return Ok(vec![]); }
+  let params = params_for(&model_path, scales, score_threshold, iou);
+  check_params(&params)?;
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let img = crate::pdf::render_page(&doc, page, dpi)?;
+    detect_pyramid(&model_path, &img, &params)
+  }).await.map_err(|e| e.to_string())?
 }
 
 
+
+// where a crop sits in the image it was cut from
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RegionSource {
+  // the crop's top-left in source pixels; may be fractional or negative when the crop was padded
+  pub x: f32,
+  pub y: f32,
+  pub source_width: u32,
+  pub source_height: u32,
+  // crop pixels per source pixel when the crop was resampled (e.g. cut from a zoomed canvas); default 1
+  #[serde(default)]
+  pub scale: Option<f32>,
+}
+
+// boxes for a crop, in subpixel source-image coordinates and clipped to the source; boxes wholly in padding are dropped
+pub fn to_source_region(dets: Vec<Det>, src: &RegionSource) -> Vec<Det> {
+  let k = src.scale.unwrap_or(1.0);
+  let (sw, sh) = (src.source_width as f32, src.source_height as f32);
+  dets.into_iter().filter_map(|d| {
+    let (x0, y0) = ((src.x + d.x / k).clamp(0.0, sw), (src.y + d.y / k).clamp(0.0, sh));
+    let (x1, y1) = ((src.x + (d.x + d.w) / k).clamp(0.0, sw), (src.y + (d.y + d.h) / k).clamp(0.0, sh));
+    (x1 > x0 && y1 > y0).then(|| Det { x: x0, y: y0, w: x1 - x0, h: y1 - y0, ..d })
+  }).collect()
+}
+
+// detection on one tile or padded crop of a larger image; boxes come back in that image's pixels
+#[tauri::command]
+pub async fn detect_region(image_base64: String, source: RegionSource, scales: Option<Vec<f32>>, score_threshold: Option<f32>, iou: Option<f32>) -> Result<Vec<Det>, String> {
+  if ![source.x, source.y].iter().all(|v| v.is_finite()) { return Err("invalid_region: crop origin must be finite".into()); }
+  if source.source_width == 0 || source.source_height == 0 { return Err("invalid_region: source image is empty".into()); }
+  if let Some(k) = source.scale.filter(|k| !k.is_finite() || *k <= 0.0) { return Err(format!("invalid_region: scale must be positive, got {k}")); }
+  let bytes = BASE64.decode(image_base64).map_err(|e| e.to_string())?;
+  let img = decode_image(&bytes)?;
+  let model_path = model_path();
+  if !model_path.exists() { return Ok(vec![]); }
+  let params = params_for(&model_path, scales, score_threshold, iou);
+  check_params(&params)?;
+  tauri::async_runtime::spawn_blocking(move || Ok(to_source_region(detect_pyramid(&model_path, &img, &params)?, &source)))
+    .await.map_err(|e| e.to_string())?
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LeaderLink { pub leader: usize, pub det: usize, pub text: String }
+
+fn dist_to_box(p: (f32, f32), x: f32, y: f32, w: f32, h: f32) -> f32 {
+  let dx = (x - p.0).max(p.0 - (x + w)).max(0.0);
+  let dy = (y - p.1).max(p.1 - (y + h)).max(0.0);
+  (dx * dx + dy * dy).sqrt()
+}
+
+// text near a leader's tail labels the detection nearest its tip
+pub fn link_leader_labels(leaders: &[crate::raster::Leader], runs: &[crate::ocr::TextRun], dets: &[Det], max_dist: f32) -> Vec<LeaderLink> {
+  let mut out = Vec::new();
+  for (li, l) in leaders.iter().enumerate() {
+    let text = runs.iter()
+      .map(|r| (dist_to_box(l.tail, r.x, r.y, r.w, r.h), r))
+      .filter(|(d, _)| *d <= max_dist)
+      .min_by(|a, b| a.0.total_cmp(&b.0));
+    let det = dets.iter().enumerate()
+      .map(|(i, d)| (dist_to_box(l.tip, d.x, d.y, d.w, d.h), i))
+      .filter(|(d, _)| *d <= max_dist)
+      .min_by(|a, b| a.0.total_cmp(&b.0));
+    if let (Some((_, r)), Some((_, di))) = (text, det) {
+      out.push(LeaderLink { leader: li, det: di, text: r.text.clone() });
+    }
+  }
+  out
+}
+
+// each linked leader's text becomes the callout of the detection at its tip; the first link wins
+pub fn attach_callouts(dets: &mut [Det], leaders: &[crate::raster::Leader], runs: &[crate::ocr::TextRun], max_dist: f32) {
+  for l in link_leader_labels(leaders, runs, dets, max_dist) {
+    let d = &mut dets[l.det];
+    if d.callout.is_none() { d.callout = Some(l.text.trim().to_string()); }
+  }
+}
+
+// counts of detection centers per `cell`-pixel square; rows top to bottom
+pub fn detection_density(dets: &[Det], w: u32, h: u32, cell: u32) -> Vec<Vec<u32>> {
+  let cell = cell.max(1);
+  let (gw, gh) = (w.div_ceil(cell) as usize, h.div_ceil(cell) as usize);
+  let mut grid = vec![vec![0u32; gw]; gh];
+  for d in dets {
+    let (cx, cy) = (d.x + d.w * 0.5, d.y + d.h * 0.5);
+    if cx < 0.0 || cy < 0.0 || cx >= w as f32 || cy >= h as f32 { continue; }
+    grid[(cy as u32 / cell) as usize][(cx as u32 / cell) as usize] += 1;
+  }
+  grid
+}
+
+// page render with the density grid blended on top, as base64 PNG
+#[tauri::command]
+pub async fn render_detection_heatmap(pdf_path: String, page: u32, dpi: u32, dets: Vec<Det>, cell: Option<u32>) -> Result<String, String> {
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let mut img = crate::pdf::render_page(&doc, page, dpi)?.to_rgba8();
+    let cell = cell.unwrap_or(64).max(1);
+    let grid = detection_density(&dets, img.width(), img.height(), cell);
+    crate::overlay::blend_heatmap(&mut img, &grid, cell);
+    crate::overlay::encode_png_base64(img)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// scores bucketed evenly over [0,1]; 1.0 lands in the last bin, out-of-range scores are clamped
+pub fn score_histogram(dets: &[Det], bins: usize) -> Vec<u32> {
+  let mut out = vec![0u32; bins];
+  if bins == 0 { return out; }
+  for d in dets {
+    if d.score.is_nan() { continue; }
+    let i = ((d.score.clamp(0.0, 1.0) * bins as f32) as usize).min(bins - 1);
+    out[i] += 1;
+  }
+  out
+}
+
+#[tauri::command]
+pub fn detection_score_histogram(dets: Vec<Det>, bins: Option<usize>) -> Vec<u32> {
+  score_histogram(&dets, bins.unwrap_or(20))
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ClassMetrics {
+  pub tp: u32,
+  pub fp: u32,
+  #[serde(rename = "fn")]
+  pub fn_: u32,
+  pub precision: f32,
+  pub recall: f32,
+  pub f1: f32,
+}
+
+impl ClassMetrics {
+  fn finish(mut self) -> Self {
+    let ratio = |a: u32, b: u32| if a + b == 0 { 0.0 } else { a as f32 / (a + b) as f32 };
+    self.precision = ratio(self.tp, self.fp);
+    self.recall = ratio(self.tp, self.fn_);
+    self.f1 = if self.precision + self.recall == 0.0 { 0.0 } else { 2.0 * self.precision * self.recall / (self.precision + self.recall) };
+    self
+  }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct DetMetrics { pub overall: ClassMetrics, pub per_class: std::collections::BTreeMap<String, ClassMetrics> }
+
+// greedy matching: predictions by descending score each take the best unmatched same-label truth box
+pub fn evaluate(pred: &[Det], truth: &[Det], iou_thr: f32) -> DetMetrics {
+  let mut order: Vec<usize> = (0..pred.len()).collect();
+  order.sort_by(|&a, &b| pred[b].score.total_cmp(&pred[a].score));
+  let mut matched = vec![false; truth.len()];
+  let mut per: std::collections::BTreeMap<String, ClassMetrics> = std::collections::BTreeMap::new();
+  for i in order {
+    let p = &pred[i];
+    let best = truth.iter().enumerate()
+      .filter(|(j, t)| !matched[*j] && t.label == p.label)
+      .map(|(j, t)| (j, iou(p, t)))
+      .filter(|(_, v)| *v >= iou_thr)
+      .max_by(|a, b| a.1.total_cmp(&b.1));
+    let m = per.entry(p.label.clone()).or_default();
+    match best { Some((j, _)) => { matched[j] = true; m.tp += 1; } None => m.fp += 1 }
+  }
+  for (j, t) in truth.iter().enumerate() {
+    if !matched[j] { per.entry(t.label.clone()).or_default().fn_ += 1; }
+  }
+  let mut overall = ClassMetrics::default();
+  for m in per.values() { overall.tp += m.tp; overall.fp += m.fp; overall.fn_ += m.fn_; }
+  DetMetrics { overall: overall.finish(), per_class: per.into_iter().map(|(k, m)| (k, m.finish())).collect() }
+}
+
+// a Det list, or COCO as written by export_annotations (labels from its categories)
+fn parse_dets(json: &str) -> Result<Vec<Det>, String> {
+  let v: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+  if v.is_array() { return serde_json::from_value(v).map_err(|e| e.to_string()); }
+  let names: std::collections::HashMap<u64, String> = v["categories"].as_array().into_iter().flatten()
+    .filter_map(|c| Some((c["id"].as_u64()?, c["name"].as_str()?.to_string())))
+    .collect();
+  let anns = v["annotations"].as_array().ok_or("expected a detection array or COCO annotations")?;
+  anns.iter().map(|a| {
+    let b: Vec<f32> = a["bbox"].as_array().into_iter().flatten().filter_map(|x| x.as_f64()).map(|x| x as f32).collect();
+    if b.len() != 4 { return Err(format!("annotation {} has no 4-value bbox", a["id"])); }
+    let label = a["category_id"].as_u64().and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+    Ok(Det { x: b[0], y: b[1], w: b[2], h: b[3], label, score: a["score"].as_f64().unwrap_or(1.0) as f32, callout: None })
+  }).collect()
+}
+
+#[tauri::command]
+pub fn evaluate_detection(pred_json: String, truth_json: String, iou: Option<f32>) -> Result<DetMetrics, String> {
+  let pred = parse_dets(&pred_json).map_err(|e| format!("predictions: {e}"))?;
+  let truth = parse_dets(&truth_json).map_err(|e| format!("ground truth: {e}"))?;
+  Ok(evaluate(&pred, &truth, iou.unwrap_or(NMS_IOU)))
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Cluster { pub centroid: (f32, f32), pub members: Vec<usize> }
+
+// DBSCAN over box centers; noise points (fewer than `min_pts` within `eps`, counting themselves) belong to no cluster
+pub fn cluster_detections(dets: &[Det], eps: f32, min_pts: usize) -> Vec<Cluster> {
+  let centers: Vec<(f32, f32)> = dets.iter().map(|d| (d.x + d.w / 2.0, d.y + d.h / 2.0)).collect();
+  let neighbours = |i: usize| -> Vec<usize> {
+    let (x, y) = centers[i];
+    (0..centers.len()).filter(|&j| { let (dx, dy) = (centers[j].0 - x, centers[j].1 - y); dx * dx + dy * dy <= eps * eps }).collect()
+  };
+  let mut assigned = vec![false; dets.len()];
+  let mut visited = vec![false; dets.len()];
+  let mut out = Vec::new();
+  for i in 0..dets.len() {
+    if visited[i] { continue; }
+    visited[i] = true;
+    let seed = neighbours(i);
+    if seed.len() < min_pts { continue; }
+    let mut members = vec![i];
+    assigned[i] = true;
+    let mut frontier = seed;
+    while let Some(j) = frontier.pop() {
+      if !assigned[j] { assigned[j] = true; members.push(j); }
+      if visited[j] { continue; }
+      visited[j] = true;
+      let more = neighbours(j);
+      // only core points grow the cluster; border points join but don't expand it
+      if more.len() >= min_pts { frontier.extend(more); }
+    }
+    members.sort_unstable();
+    let n = members.len() as f32;
+    let centroid = members.iter().fold((0.0, 0.0), |(sx, sy), &m| (sx + centers[m].0 / n, sy + centers[m].1 / n));
+    out.push(Cluster { centroid, members });
+  }
+  out
+}
+
+#[tauri::command]
+pub fn cluster_symbols(dets: Vec<Det>, eps: f32, min_pts: Option<usize>) -> Result<Vec<Cluster>, String> {
+  if eps.is_nan() || eps <= 0.0 { return Err(format!("eps must be positive, got {eps}")); }
+  Ok(cluster_detections(&dets, eps, min_pts.unwrap_or(3)))
+}
+
+// a sheet legend: the key drawing of each symbol, usually repeated in the same spot on every page.
+// What's drawn there is a sample, not a fixture.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct LegendRegion {
+  pub page: u32,
+  // (x0, y0, x1, y1) in page points, applied to every page
+  pub rect: (f32, f32, f32, f32),
+}
+
+// what a legend entry looks like, to recognise the same key on other pages; size in pixels of the run
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LegendSymbol { pub label: String, pub w: f32, pub h: f32 }
+
+const LEGEND_SIZE_TOL: f32 = 0.25;
+
+impl LegendSymbol {
+  pub fn of(d: &Det) -> Self { LegendSymbol { label: d.label.clone(), w: d.w, h: d.h } }
+
+  pub fn matches(&self, d: &Det) -> bool {
+    let close = |a: f32, b: f32| (a - b).abs() <= LEGEND_SIZE_TOL * a.max(b);
+    self.label == d.label && close(self.w, d.w) && close(self.h, d.h)
+  }
+}
+
+// by pdf path, so each open document (or compare window) keeps its own
+static LEGEND: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashMap<String, LegendRegion>>> = once_cell::sync::Lazy::new(Default::default);
+
+pub fn legend(pdf_path: &str) -> Option<LegendRegion> { LEGEND.lock().get(pdf_path).copied() }
+
+// (outside, inside) by box centre; `rect` is (x0, y0, x1, y1) in the detections' pixels
+pub fn split_by_rect(dets: Vec<Det>, rect: (f32, f32, f32, f32)) -> (Vec<Det>, Vec<Det>) {
+  dets.into_iter().partition(|d| {
+    let (cx, cy) = (d.x + d.w * 0.5, d.y + d.h * 0.5);
+    !(cx >= rect.0 && cx <= rect.2 && cy >= rect.1 && cy <= rect.3)
+  })
+}
+
+// marks where the legend is; takeoffs started afterwards drop what's detected inside it on `page`,
+// and anything inside the same area on other pages that looks like one of its entries. No rect clears it.
+#[tauri::command]
+pub fn set_legend_region(pdf_path: String, page: u32, rect: Option<(f32, f32, f32, f32)>) -> Result<(), String> {
+  let Some(rect) = rect else { LEGEND.lock().remove(&pdf_path); return Ok(()) };
+  let (x0, y0, x1, y1) = rect;
+  if ![x0, y0, x1, y1].iter().all(|v| v.is_finite()) || x1 <= x0 || y1 <= y0 { return Err(format!("invalid region ({x0},{y0})-({x1},{y1})")); }
+  LEGEND.lock().insert(pdf_path, LegendRegion { page, rect });
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bad_pyramid_scales_are_rejected() {
+    for bad in [f32::NAN, 0.0, -1.0, 1e6, f32::INFINITY] {
+      assert!(check_params(&DetectParams { scales: vec![1.0, bad], ..Default::default() }).is_err(), "{bad} accepted");
+    }
+    assert!(check_params(&DetectParams { scales: vec![0.5, 1.0, MAX_PYRAMID_SCALE], ..Default::default() }).is_ok());
+  }
+
+  #[test]
+  fn tiles_cover_the_image_with_overlap() {
+    assert_eq!(tile_origins(500, 0, 64), vec![0]);
+    assert_eq!(tile_origins(500, 640, 64), vec![0]);
+    assert_eq!(tile_origins(1000, 400, 50), vec![0, 350, 600]);
+    assert!(check_params(&DetectParams { tile: 400, tile_overlap: 250, ..Default::default() }).is_err());
+    assert!(check_params(&DetectParams { tile: 10, ..Default::default() }).is_err());
+  }
+
+  #[test]
+  fn callout_text_goes_to_the_symbol_at_the_tip() {
+    let det = |x: f32| Det { x, y: 100.0, w: 20.0, h: 20.0, label: "stud".into(), score: 0.9, callout: None };
+    let mut dets = vec![det(100.0), det(300.0)];
+    // the leader starts by the text over the first stud and kinks down to the second
+    let leader = crate::raster::Leader { tip: (305.0, 98.0), tail: (110.0, 40.0), points: vec![(305.0, 98.0), (305.0, 40.0), (110.0, 40.0)] };
+    let runs = [crate::ocr::TextRun { text: "2x4 ".into(), x: 80.0, y: 30.0, w: 26.0, h: 14.0, conf: 90.0 }];
+    attach_callouts(&mut dets, &[leader], &runs, 12.0);
+    assert_eq!(dets[0].callout, None);
+    assert_eq!(dets[1].callout.as_deref(), Some("2x4"));
+  }
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+use tauri::Manager;
+
+// environment state behind otherwise silent failures, for a "System Check" panel and bug reports
+#[derive(Clone, Serialize, Debug)]
+pub struct Diagnostics {
+  pub version: String,
+  pub os: String,
+  pub arch: String,
+  // "system" or "builtin"; None with `pdfium_error` when neither loads
+  pub pdfium: Option<String>,
+  pub pdfium_error: Option<String>,
+  pub model_path: String,
+  pub model_exists: bool,
+  pub model_loaded: bool,
+  pub execution_providers: Vec<String>,
+  // false: OCR runs in the frontend (tesseract.js) only
+  pub tesseract: bool,
+  pub tesseract_languages: Vec<String>,
+  pub config_dir: Option<String>,
+  pub data_dir: Option<String>,
+  pub log_dir: Option<String>,
+}
+
+#[tauri::command]
+pub async fn diagnostics(app: tauri::AppHandle) -> Diagnostics {
+  let dir = |r: tauri::Result<std::path::PathBuf>| r.ok().map(|p| p.to_string_lossy().to_string());
+  let (pdfium, pdfium_error) = match crate::pdf::bind_source() {
+    Ok(src) => (Some(src.to_string()), None),
+    Err(e) => (None, Some(e)),
+  };
+  let model = crate::detect::model_path();
+  let model_exists = model.exists();
+  let tesseract = crate::ocr::engine_info();
+  Diagnostics {
+    version: app.package_info().version.to_string(),
+    os: std::env::consts::OS.into(),
+    arch: std::env::consts::ARCH.into(),
+    pdfium,
+    pdfium_error,
+    model_path: model.to_string_lossy().to_string(),
+    model_exists,
+    model_loaded: model_exists && crate::detect::model_loads(&model),
+    execution_providers: crate::detect::execution_providers().into_iter().map(String::from).collect(),
+    tesseract: tesseract.is_some(),
+    tesseract_languages: tesseract.unwrap_or_default(),
+    config_dir: dir(app.path().app_config_dir()),
+    data_dir: dir(app.path().app_data_dir()),
+    log_dir: dir(app.path().app_log_dir()),
+  }
+}
@@ -0,0 +1,8 @@
+use tauri::Emitter;
+
+// for code that runs off the command threads (watchers, background renders): the caller hands in the
+// AppHandle it was started with, so nothing can emit before setup has one to give.
+// To every window; events meant for one window should still go through emit_to on its label.
+pub fn emit_global<S: serde::Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) {
+  if let Err(e) = app.emit(event, payload) { log::warn!("{event} dropped: {e}"); }
+}
@@ -0,0 +1,289 @@
+use crate::jobs::TakeoffResult;
+use crate::overlay::Overlay;
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize { #[default] Letter, Legal, Tabloid, A4, A3, ArchD }
+
+impl PaperSize {
+  // portrait (width, height) in points
+  fn points(self) -> (f32, f32) {
+    match self {
+      PaperSize::Letter => (612.0, 792.0),
+      PaperSize::Legal => (612.0, 1008.0),
+      PaperSize::Tabloid => (792.0, 1224.0),
+      PaperSize::A4 => (595.0, 842.0),
+      PaperSize::A3 => (842.0, 1191.0),
+      PaperSize::ArchD => (1728.0, 2592.0),
+    }
+  }
+}
+
+const MARGIN_PT: f32 = 24.0;
+
+// one-page PDF with the marked-up render fitted to the paper (rotated to match its aspect),
+// path labels placed as real text at their first point and notes along the bottom margin
+fn write_markup_pdf(lib: &Pdfium, img: &image::RgbaImage, ov: &Overlay, paper: PaperSize, out: &std::path::Path) -> Result<(), String> {
+  let (mut pw, mut ph) = paper.points();
+  if img.width() > img.height() { std::mem::swap(&mut pw, &mut ph); }
+  let mut doc = lib.create_new_pdf().map_err(|e| e.to_string())?;
+  let font = doc.fonts_mut().helvetica();
+  let mut page = doc.pages_mut()
+    .create_page_at_end(PdfPagePaperSize::from_points(PdfPoints::new(pw), PdfPoints::new(ph)))
+    .map_err(|e| e.to_string())?;
+
+  let notes_h = if ov.notes.is_empty() { 0.0 } else { 14.0 * ov.notes.len() as f32 };
+  let (aw, ah) = (pw - 2.0 * MARGIN_PT, ph - 2.0 * MARGIN_PT - notes_h);
+  let s = (aw / img.width() as f32).min(ah / img.height() as f32);
+  let (iw, ih) = (img.width() as f32 * s, img.height() as f32 * s);
+  let (ox, oy) = (MARGIN_PT + (aw - iw) * 0.5, MARGIN_PT + notes_h + (ah - ih) * 0.5);
+  let dynimg = image::DynamicImage::ImageRgba8(img.clone());
+  page.objects_mut()
+    .create_image_object(PdfPoints::new(ox), PdfPoints::new(oy), &dynimg, Some(PdfPoints::new(iw)), Some(PdfPoints::new(ih)))
+    .map_err(|e| e.to_string())?;
+
+  let to_pt = |p: (f32, f32)| (ox + p.0 * s, oy + (img.height() as f32 - p.1) * s);
+  for p in &ov.paths {
+    let (Some(label), Some(&first)) = (&p.label, p.points.first()) else { continue };
+    let (x, y) = to_pt(first);
+    page.objects_mut().create_text_object(PdfPoints::new(x + 2.0), PdfPoints::new(y + 2.0), label, font, PdfPoints::new(8.0))
+      .map_err(|e| e.to_string())?;
+  }
+  for (i, n) in ov.notes.iter().enumerate() {
+    let y = MARGIN_PT + notes_h - 14.0 * (i as f32 + 1.0) + 3.0;
+    page.objects_mut().create_text_object(PdfPoints::new(MARGIN_PT), PdfPoints::new(y), n, font, PdfPoints::new(10.0))
+      .map_err(|e| e.to_string())?;
+  }
+  doc.save_to_file(out).map_err(|e| e.to_string())
+}
+
+fn send_to_printer(path: &std::path::Path) -> Result<(), String> {
+  #[cfg(target_os = "windows")]
+  {
+    let cmd = format!("Start-Process -FilePath '{}' -Verb Print", path.display().to_string().replace('\'', "''"));
+    std::process::Command::new("powershell").args(["-NoProfile", "-Command", &cmd]).spawn().map(|_| ()).map_err(|e| e.to_string())
+  }
+  #[cfg(not(target_os = "windows"))]
+  {
+    // no shell print verb here; open in the default viewer, which owns the print dialog
+    tauri_plugin_shell::open::that(path.to_string_lossy().to_string()).map_err(|e| e.to_string())
+  }
+}
+
+#[tauri::command]
+pub async fn print_page(pdf_path: String, page: u32, dpi: Option<u32>, overlay: Overlay, paper: Option<PaperSize>) -> Result<String, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| e.to_string())?;
+    let mut img = crate::pdf::render_page(&doc, page, dpi)?.to_rgba8();
+    crate::overlay::draw_overlay(&mut img, &overlay);
+    let out = std::env::temp_dir().join(format!("betterbeam_print_p{}_{}.pdf", page + 1, std::process::id()));
+    write_markup_pdf(lib, &img, &overlay, paper.unwrap_or_default(), &out)?;
+    send_to_printer(&out)?;
+    Ok(out.to_string_lossy().to_string())
+  }).await.map_err(|e| e.to_string())?
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CompanyInfo {
+  pub name: String,
+  pub address: Vec<String>,
+  pub phone: String,
+  pub email: String,
+  pub logo_path: Option<String>,
+}
+
+const QUOTE_W: f32 = 612.0;
+const QUOTE_H: f32 = 792.0;
+const QUOTE_MARGIN: f32 = 48.0;
+const ROW_H: f32 = 14.0;
+// sku, description, qty, unit, extended
+const COLS: [f32; 5] = [48.0, 150.0, 380.0, 440.0, 510.0];
+
+fn text(page: &mut PdfPage, x: f32, y: f32, s: &str, font: PdfFontToken, size: f32) -> Result<(), String> {
+  page.objects_mut().create_text_object(PdfPoints::new(x), PdfPoints::new(y), s, font, PdfPoints::new(size))
+    .map(|_| ()).map_err(|e| e.to_string())
+}
+
+// a printable line: (column x, text, bold)
+type Row = Vec<(f32, String, bool)>;
+
+fn quote_rows(r: &TakeoffResult) -> Vec<Row> {
+  let cur = r.summary.currency.as_str();
+  let money = |v: f64| crate::map::format_money(v, cur);
+  let mut rows: Vec<Row> = Vec::new();
+  for it in &r.items {
+    let ext = it.qty as f64 * it.unit_price;
+    rows.push(vec![
+      (COLS[0], it.sku.clone(), false),
+      (COLS[1], format!("{} / {}", it.material, it.finish), false),
+      (COLS[2], it.qty.to_string(), false),
+      (COLS[3], money(it.unit_price), false),
+      (COLS[4], money(ext), false),
+    ]);
+  }
+  rows.push(vec![]);
+  let s = &r.summary;
+  rows.push(vec![(COLS[3], "Subtotal".into(), false), (COLS[4], money(s.subtotal), false)]);
+  for (label, v) in [("Overhead", s.overhead), ("Profit", s.profit), ("Tax", s.tax)] {
+    if v != 0.0 { rows.push(vec![(COLS[3], label.into(), false), (COLS[4], money(v), false)]); }
+  }
+  rows.push(vec![(COLS[3], "Total".into(), true), (COLS[4], money(s.total), true)]);
+  rows.push(vec![]);
+  rows.push(vec![(COLS[0], "Symbol counts".into(), true)]);
+  if let Some(m) = r.summary.symbols.as_object() {
+    for (label, n) in m {
+      let shown = match n.get("count") { Some(c) => format!("{c} (~{:.1})", n["expected"].as_f64().unwrap_or(0.0)), None => n.to_string() };
+      rows.push(vec![(COLS[0], label.clone(), false), (COLS[2], shown, false)]);
+    }
+  }
+  if !r.unmapped.is_empty() {
+    rows.push(vec![]);
+    rows.push(vec![(COLS[0], "Not in catalog (not priced)".into(), true)]);
+    for u in &r.unmapped { rows.push(vec![(COLS[0], u.label.clone(), false), (COLS[2], u.count.to_string(), false)]); }
+    rows.push(vec![]);
+  }
+  rows.push(vec![(COLS[0], "Lineal (ft)".into(), false), (COLS[2], format!("{:.1}", r.summary.lineal_feet), false)]);
+  for (label, ft) in &r.summary.lineal_by_label {
+    rows.push(vec![(COLS[0], format!("  {label} (ft)"), false), (COLS[2], format!("{ft:.1}"), false)]);
+  }
+  rows.push(vec![(COLS[0], "Area (sq ft)".into(), false), (COLS[2], format!("{:.1}", r.summary.area_sqft), false)]);
+  rows
+}
+
+fn write_quote(lib: &Pdfium, r: &TakeoffResult, company: &CompanyInfo, out: &std::path::Path) -> Result<(), String> {
+  let mut doc = lib.create_new_pdf().map_err(|e| e.to_string())?;
+  let font = doc.fonts_mut().helvetica();
+  let bold = doc.fonts_mut().helvetica_bold();
+  let logo = company.logo_path.as_deref().and_then(|p| image::open(p).ok());
+  let rows = quote_rows(r);
+  let size = PdfPagePaperSize::from_points(PdfPoints::new(QUOTE_W), PdfPoints::new(QUOTE_H));
+
+  let header_h = 110.0;
+  let first_cap = ((QUOTE_H - 2.0 * QUOTE_MARGIN - header_h - ROW_H * 2.0) / ROW_H) as usize;
+  let rest_cap = ((QUOTE_H - 2.0 * QUOTE_MARGIN - ROW_H * 2.0) / ROW_H) as usize;
+  let pages = 1 + rows.len().saturating_sub(first_cap).div_ceil(rest_cap);
+
+  let mut remaining = &rows[..];
+  for pi in 0..pages {
+    let mut page = doc.pages_mut().create_page_at_end(size).map_err(|e| e.to_string())?;
+    let mut y = QUOTE_H - QUOTE_MARGIN;
+    if pi == 0 {
+      let mut tx = QUOTE_MARGIN;
+      if let Some(img) = &logo {
+        page.objects_mut()
+          .create_image_object(PdfPoints::new(QUOTE_MARGIN), PdfPoints::new(y - 48.0), img, None, Some(PdfPoints::new(48.0)))
+          .map_err(|e| e.to_string())?;
+        tx += 48.0 * img.width() as f32 / img.height().max(1) as f32 + 12.0;
+      }
+      text(&mut page, tx, y - 16.0, &company.name, bold, 16.0)?;
+      let mut ly = y - 30.0;
+      for line in company.address.iter().chain([&company.phone, &company.email]).filter(|l| !l.is_empty()) {
+        text(&mut page, tx, ly, line, font, 9.0)?;
+        ly -= 11.0;
+      }
+      let sheet = r.sheet.as_ref().map(|s| format!(" – sheet {}", s.number)).unwrap_or_default();
+      let name = std::path::Path::new(&r.pdf_path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+      text(&mut page, QUOTE_MARGIN, y - header_h + 24.0, &format!("Quote for {name}{sheet}"), bold, 11.0)?;
+      y -= header_h;
+    }
+    for (x, h) in COLS.iter().zip(["SKU", "Description", "Qty", "Unit", "Extended"]) { text(&mut page, *x, y, h, bold, 9.0)?; }
+    y -= ROW_H * 1.5;
+    let cap = if pi == 0 { first_cap } else { rest_cap };
+    let (now, later) = remaining.split_at(cap.min(remaining.len()));
+    for row in now {
+      for (x, s, b) in row { text(&mut page, *x, y, s, if *b { bold } else { font }, 9.0)?; }
+      y -= ROW_H;
+    }
+    remaining = later;
+    text(&mut page, QUOTE_W - QUOTE_MARGIN - 50.0, QUOTE_MARGIN * 0.5, &format!("Page {} of {pages}", pi + 1), font, 8.0)?;
+  }
+  doc.save_to_file(out).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_quote_pdf(result_json: String, out_path: String, company: CompanyInfo) -> Result<String, String> {
+  let result: TakeoffResult = serde_json::from_str(&result_json).map_err(|e| format!("result_json: {e}"))?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    write_quote(lib, &result, &company, std::path::Path::new(&out_path))?;
+    Ok(out_path)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// COCO-style annotations for one page image. `dpi` is the render the boxes were detected on;
+// category ids follow the sorted label names so repeated exports agree.
+#[tauri::command]
+pub async fn export_annotations(pdf_path: String, page: u32, dets: Vec<crate::detect::Det>, out_path: String, dpi: Option<u32>) -> Result<String, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let (w_px, h_px) = {
+      let lib = crate::pdf::bind()?;
+      let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+      crate::transform::Transform::for_page(&doc, page, dpi)?.pixel_size()
+    };
+    let (width, height) = (w_px.round() as u32, h_px.round() as u32);
+
+    let labels: std::collections::BTreeSet<&str> = dets.iter().map(|d| d.label.as_str()).collect();
+    let cat_id = |l: &str| labels.iter().position(|x| *x == l).map(|i| i + 1).unwrap_or(0);
+    let stem = std::path::Path::new(&pdf_path).file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "page".into());
+    let coco = serde_json::json!({
+      "images": [{ "id": 1, "file_name": format!("{stem}_p{}.png", page + 1), "width": width, "height": height }],
+      "annotations": dets.iter().enumerate().map(|(i, d)| serde_json::json!({
+        "id": i + 1,
+        "image_id": 1,
+        "category_id": cat_id(&d.label),
+        "bbox": [d.x, d.y, d.w, d.h],
+        "area": d.w * d.h,
+        "iscrowd": 0,
+        "score": d.score,
+      })).collect::<Vec<_>>(),
+      "categories": labels.iter().enumerate().map(|(i, l)| serde_json::json!({ "id": i + 1, "name": l })).collect::<Vec<_>>(),
+    });
+    let text = serde_json::to_string_pretty(&coco).map_err(|e| e.to_string())?;
+    std::fs::write(&out_path, text).map_err(|e| format!("{out_path}: {e}"))?;
+    Ok(out_path)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// "p003_E2_1.png" when the title block gives a sheet number, else "p003.png"; one-based like print names
+fn overlay_file_name(page: u32, sheet: Option<&crate::sheet::SheetId>) -> String {
+  match sheet {
+    Some(s) => format!("p{:03}_{}.png", page + 1, s.file_stem()),
+    None => format!("p{:03}.png", page + 1),
+  }
+}
+
+// every page rendered with its overlay into `out_dir`, one PNG per page; `overlays` is keyed by
+// zero-based page and pages without one are written bare. Reports `export:progress` to the caller.
+#[tauri::command]
+pub async fn export_all_overlays(window: tauri::Window, pdf_path: String, out_dir: String, dpi: Option<u32>, overlays: std::collections::HashMap<u32, Overlay>) -> Result<Vec<String>, String> {
+  use tauri::Emitter;
+  let owner = window.label().to_string();
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("{out_dir}: {e}"))?;
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let pages = doc.pages().len() as u32;
+    let mut files = Vec::with_capacity(pages as usize);
+    for page in 0..pages {
+      let mut img = crate::pdf::render_page(&doc, page, dpi)?.to_rgba8();
+      if let Some(ov) = overlays.get(&page) { crate::overlay::draw_overlay(&mut img, ov); }
+      // text layer only; scanned sheets just get the page number
+      let sheet = crate::sheet::page_sheet_id(&doc, page);
+      let out = std::path::Path::new(&out_dir).join(overlay_file_name(page, sheet.as_ref()));
+      img.save(&out).map_err(|e| format!("{}: {e}", out.display()))?;
+      files.push(out.to_string_lossy().to_string());
+      let _ = window.emit_to(&owner, "export:progress", serde_json::json!({ "page": page, "done": page + 1, "pages": pages, "file": files.last() }));
+    }
+    Ok(files)
+  }).await.map_err(|e| e.to_string())?
+}
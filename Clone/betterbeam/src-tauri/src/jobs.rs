@@ -1,7 +1,9 @@
+use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{collections::HashMap, sync::atomic::{AtomicU64, Ordering}};
+use std::{collections::HashMap, sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}}};
+use tauri::{AppHandle, Emitter};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct JobProgress {
@@ -15,6 +17,7 @@ pub enum JobState {
   Running(JobProgress),
   Succeeded,
   Failed(String),
+  Cancelled,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -22,10 +25,168 @@ pub struct Job {
   pub id: u64,
   pub state: JobState,
   pub result_json: Option<String>,
+  // label of the window that started the job; its events go only there
+  #[serde(default)]
+  pub owner: String,
+  // the job's own log lines, newest last; kept for export_error_report
+  #[serde(default, skip_serializing)]
+  pub log: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct VectorStats {
+  pub segments: usize,
+  // by orientation, within ORTHO_TOL_DEG of the axis; the rest are diagonal
+  #[serde(default)]
+  pub horizontal: usize,
+  #[serde(default)]
+  pub vertical: usize,
+  #[serde(default)]
+  pub diagonal: usize,
+  // summed segment length, pixels
+  #[serde(default)]
+  pub length_px: f64,
+}
+
+const ORTHO_TOL_DEG: f32 = 2.0;
+
+impl VectorStats {
+  pub fn from_lines(lines: &[crate::raster::PseudoLine]) -> Self {
+    let mut s = VectorStats { segments: lines.len(), ..Default::default() };
+    for l in lines {
+      let (dx, dy) = (l.x1 - l.x0, l.y1 - l.y0);
+      s.length_px += dx.hypot(dy) as f64;
+      // fold into 0..=90 so direction doesn't matter
+      let ang = dy.abs().atan2(dx.abs()).to_degrees();
+      if ang <= ORTHO_TOL_DEG { s.horizontal += 1; } else if ang >= 90.0 - ORTHO_TOL_DEG { s.vertical += 1; } else { s.diagonal += 1; }
+    }
+    s
+  }
+
+  fn add(&mut self, o: &VectorStats) {
+    self.segments += o.segments;
+    self.horizontal += o.horizontal;
+    self.vertical += o.vertical;
+    self.diagonal += o.diagonal;
+    self.length_px += o.length_px;
+  }
+}
+
+// schema of result_json; built as a struct so the fields can't drift from what the frontend reads
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TakeoffResult {
+  pub pdf_path: String,
+  pub pages: u32,
+  pub units_per_pixel: f32,
+  pub vectors: VectorStats,
+  pub summary: crate::map::MappingSummary,
+  pub items: Vec<crate::map::LineItem>,
+  // true while the job is still running and later stages haven't filled in their fields yet
+  #[serde(default)]
+  pub partial: bool,
+  #[serde(default)]
+  pub sheet: Option<crate::sheet::SheetId>,
+  #[serde(default)]
+  pub warnings: Vec<String>,
+  // per-region subtotals; `summary`/`items` are the grand total
+  #[serde(default)]
+  pub regions: std::collections::BTreeMap<String, crate::map::MappingResult>,
+  // printed dimensions compared with what the scale measures for their lines
+  #[serde(default)]
+  pub dimension_checks: Vec<crate::scale::DimensionCheck>,
+  // from a quick run: low dpi, one detector scale, no vectors. Approximate.
+  #[serde(default)]
+  pub preview: bool,
+  // (x0, y0, x1, y1) in page points when the takeoff was limited to part of the sheet
+  #[serde(default)]
+  pub region: Option<(f32, f32, f32, f32)>,
+  // detected labels the catalog has no row for; see map::MappingResult
+  #[serde(default)]
+  pub unmapped: Vec<crate::map::UnmappedLabel>,
+  // the scale each counted page was measured with
+  #[serde(default)]
+  pub page_scales: std::collections::BTreeMap<u32, crate::scale::PageScale>,
+  // the text the scale was inferred from; None when it came from a scale bar or the default
+  #[serde(default)]
+  pub scale_match: Option<crate::scale::ScaleMatch>,
 }
 
 static NEXT_ID: AtomicU64 = AtomicU64::new(1);
-static JOBS: Mutex<HashMap<u64, Job>> = Mutex::new(HashMap::new());
+static JOBS: Lazy<Mutex<HashMap<u64, Job>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// cancel flags for jobs still running; removed when the pipeline returns
+static CANCEL: Lazy<Mutex<HashMap<u64, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// finished jobs, persisted so results survive a restart; loaded ids are never handed out again
+static STORE: once_cell::sync::OnceCell<std::path::PathBuf> = once_cell::sync::OnceCell::new();
+
+pub fn init(app: &AppHandle) {
+  use tauri::Manager;
+  let Ok(dir) = app.path().app_data_dir() else { return };
+  let path = dir.join("jobs.json");
+  if let Ok(text) = std::fs::read_to_string(&path) {
+    match serde_json::from_str::<Vec<Job>>(&text) {
+      Ok(jobs) => {
+        let max = jobs.iter().map(|j| j.id).max().unwrap_or(0);
+        JOBS.lock().extend(jobs.into_iter().map(|j| (j.id, j)));
+        NEXT_ID.fetch_max(max + 1, Ordering::SeqCst);
+      }
+      Err(e) => log::warn!("ignoring unreadable job store {}: {e}", path.display()),
+    }
+  }
+  let _ = STORE.set(path);
+}
+
+// one writer at a time; a job finishing during shutdown would otherwise race it on the temp file
+static PERSIST: Mutex<()> = Mutex::new(());
+
+fn persist() {
+  let Some(path) = STORE.get() else { return };
+  let _guard = PERSIST.lock();
+  let done: Vec<Job> = JOBS.lock().values()
+    .filter(|j| matches!(j.state, JobState::Succeeded | JobState::Failed(_) | JobState::Cancelled))
+    .cloned()
+    .collect();
+  // via a temp file and rename, so quitting mid-write leaves the old store rather than half a new one
+  let write = || -> Result<(), String> {
+    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir).map_err(|e| e.to_string())?; }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(&done).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+  };
+  if let Err(e) = write() { log::warn!("saving job store {}: {e}", path.display()); }
+}
+
+// registers a Pending job and returns its id. An explicit id must be free; otherwise the counter
+// moves past any id already taken (loaded from the store, or left over from before a reset).
+fn claim_id(explicit: Option<u64>, owner: &str) -> Result<u64, String> {
+  claim_in(&mut JOBS.lock(), &NEXT_ID, explicit, owner)
+}
+
+// claim_id against the given table and counter
+fn claim_in(jobs: &mut HashMap<u64, Job>, next: &AtomicU64, explicit: Option<u64>, owner: &str) -> Result<u64, String> {
+  let id = match explicit {
+    Some(0) => return Err("job id 0 is reserved".into()),
+    Some(id) if jobs.contains_key(&id) => return Err(format!("job id {id} is already in use")),
+    Some(id) => id,
+    None => loop {
+      let id = next.fetch_add(1, Ordering::SeqCst);
+      if !jobs.contains_key(&id) { break id; }
+    },
+  };
+  jobs.insert(id, Job { id, state: JobState::Pending, result_json: None, owner: owner.to_string(), log: Vec::new() });
+  Ok(id)
+}
+
+// for tests and reproducible runs: ids count from 1 again. Only in debug builds, or when
+// BETTERBEAM_TEST is set, so a release build can't be talked into reusing ids by accident.
+#[tauri::command]
+pub fn reset_job_counter() -> Result<(), String> {
+  if !cfg!(debug_assertions) && std::env::var_os("BETTERBEAM_TEST").is_none() {
+    return Err("reset_job_counter is only available in test builds".into());
+  }
+  NEXT_ID.store(1, Ordering::SeqCst);
+  Ok(())
+}
 
 fn set_state(id: u64, state: JobState) {
   if let Some(job) = JOBS.lock().get_mut(&id) { job.state = state; }
@@ -34,62 +195,574 @@ fn set_result(id: u64, result: String) {
   if let Some(job) = JOBS.lock().get_mut(&id) { job.result_json = Some(result); }
 }
 
+const JOB_LOG_LINES: usize = 200;
+
+// logs under the `jobs` target and keeps a bounded copy on the job for error reports
+fn job_log(id: u64, level: log::Level, line: String) {
+  log::log!(target: "jobs", level, "job={id} {line}");
+  if let Some(job) = JOBS.lock().get_mut(&id) {
+    if job.log.len() >= JOB_LOG_LINES { job.log.remove(0); }
+    job.log.push(format!("{level} {line}"));
+  }
+}
+
+// stages whose duration we can't estimate; the taskbar shows these as indeterminate
+const INDETERMINATE_STAGES: &[&str] = &["open"];
+
+fn emit_progress(app: &AppHandle, owner: &str, id: u64, stage: &str, pct: u8, eta_ms: Option<u64>) {
+  let _ = app.emit_to(owner, "job:progress", json!({
+    "id": id, "window": owner, "stage": stage, "pct": pct, "indeterminate": INDETERMINATE_STAGES.contains(&stage), "eta_ms": eta_ms,
+  }));
+}
+
+// time remaining from a moving average of the gaps between recent page completions.
+// Concurrency is already baked into the gaps, so no division by thread count.
+struct Eta {
+  last: std::time::Instant,
+  recent: std::collections::VecDeque<f64>,
+}
+
+const ETA_WINDOW: usize = 8;
+
+impl Eta {
+  fn new() -> Self { Eta { last: std::time::Instant::now(), recent: Default::default() } }
+
+  fn page_done(&mut self, remaining: u64) -> u64 {
+    let now = std::time::Instant::now();
+    self.recent.push_back(now.duration_since(self.last).as_secs_f64() * 1000.0);
+    if self.recent.len() > ETA_WINDOW { self.recent.pop_front(); }
+    self.last = now;
+    let avg = self.recent.iter().sum::<f64>() / self.recent.len() as f64;
+    (avg * remaining as f64) as u64
+  }
+}
+
+const CANCELLED: &str = "cancelled";
+
+// paint everything outside the region (page points) white, so no stage sees ink there
+fn mask_outside(img: &mut image::DynamicImage, t: &crate::transform::Transform, r: (f32, f32, f32, f32)) {
+  let corners = [(r.0, r.1), (r.2, r.1), (r.0, r.3), (r.2, r.3)].map(|p| t.point_to_pixel(p));
+  let (x0, x1) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), c| (lo.min(c.0), hi.max(c.0)));
+  let (y0, y1) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), c| (lo.min(c.1), hi.max(c.1)));
+  let mut rgba = img.to_rgba8();
+  for (x, y, p) in rgba.enumerate_pixels_mut() {
+    let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+    if fx < x0 || fx > x1 || fy < y0 || fy > y1 { *p = image::Rgba([255, 255, 255, 255]); }
+  }
+  *img = image::DynamicImage::ImageRgba8(rgba);
+}
+
+// per-run context handed to every stage
+struct Ctx {
+  app: AppHandle,
+  owner: String,
+  id: u64,
+  cancel: Arc<AtomicBool>,
+  retries: u32,
+  quick: bool,
+  // the circle_detection setting, captured at start
+  circles: bool,
+  region: Option<(f32, f32, f32, f32)>,
+  // captured at start so moving the legend mid-run doesn't split the set between two rules
+  legend: Option<crate::detect::LegendRegion>,
+  // set_page_scale overrides in pixels of this run's dpi
+  page_scales: std::collections::BTreeMap<u32, f32>,
+  // fields published so far; mirrored into result_json after every stage
+  partial: Mutex<serde_json::Map<String, serde_json::Value>>,
+  eta: Mutex<Eta>,
+  // page bitmaps keyed by (page, dpi), shared by every stage that looks at pixels
+  renders: Mutex<HashMap<(u32, u32), Arc<image::DynamicImage>>>,
+}
+
+impl Ctx {
+  fn cancelled(&self) -> bool { self.cancel.load(Ordering::SeqCst) }
+
+  fn update(&self, stage: &str, pct: u8) -> Result<(), String> { self.update_eta(stage, pct, None) }
+
+  fn update_eta(&self, stage: &str, pct: u8, eta_ms: Option<u64>) -> Result<(), String> {
+    if self.cancelled() { return Err(CANCELLED.into()); }
+    job_log(self.id, log::Level::Info, format!("stage={stage} pct={pct}"));
+    set_state(self.id, JobState::Running(JobProgress { stage: stage.to_string(), pct }));
+    emit_progress(&self.app, &self.owner, self.id, stage, pct, eta_ms);
+    Ok(())
+  }
+
+  fn publish(&self, key: &str, value: serde_json::Value) {
+    let mut m = self.partial.lock();
+    m.insert(key.to_string(), value);
+    m.insert("partial".into(), json!(true));
+    set_result(self.id, serde_json::Value::Object(m.clone()).to_string());
+  }
+
+  // renders at most once per (page, dpi) so every stage sees the same pixels at the same scale
+  fn render(&self, doc: &pdfium_render::prelude::PdfDocument, page: u32, dpi: u32) -> Result<Arc<image::DynamicImage>, String> {
+    if let Some(img) = self.renders.lock().get(&(page, dpi)) { return Ok(img.clone()); }
+    // hard-edged grayscale: every consumer here thresholds or runs the detector
+    let mut img = self.retry(|| crate::pdf::render_page_with(doc, page, dpi, &crate::pdf::RenderFlags::analysis()))?;
+    if let Some(r) = self.region { mask_outside(&mut img, &crate::transform::Transform::for_page(doc, page, dpi)?, r); }
+    let img = Arc::new(img);
+    self.renders.lock().insert((page, dpi), img.clone());
+    Ok(img)
+  }
+
+  // a finished page's bitmaps are no longer needed; keeps memory flat on big sheet sets
+  fn release_page(&self, page: u32) {
+    self.renders.lock().retain(|&(p, _), _| p != page);
+  }
+
+  // transient failures (a file still locked by the writer, a flaky render) get a few backed-off retries
+  fn retry<T>(&self, mut f: impl FnMut() -> Result<T, String>) -> Result<T, String> {
+    let r = crate::raster::retry_with_backoff(|| f().map_err(anyhow::Error::msg), self.retries, &|| self.cancelled());
+    if self.cancelled() { return Err(CANCELLED.into()); }
+    r.map_err(|e| e.to_string())
+  }
+}
+
+// what one page contributes to the takeoff
+#[derive(Default)]
+struct PageOutput {
+  vectors: VectorStats,
+  sheet: Option<crate::sheet::SheetId>,
+  scale: Option<f32>,
+  // set when `scale` was read from the page text
+  scale_match: Option<crate::scale::ScaleMatch>,
+  detections: Vec<crate::detect::Det>,
+  // detections inside the legend area, held back until the legend page says which are samples
+  legend_hits: Vec<crate::detect::Det>,
+  measurements: Vec<crate::measure::Measurement>,
+  runs: Vec<crate::ocr::TextRun>,
+  lines: Vec<crate::raster::PseudoLine>,
+  // (cx, cy, r) in pixels
+  circles: Vec<(f32, f32, f32)>,
+  // a schedule/table page; only its text is used
+  table: bool,
+}
+
+// what a page yields before detection. None of it depends on detector settings, so a rerun with
+// other thresholds picks it up from ARTIFACTS instead of redoing OCR, vectors and scale.
+struct PageArtifacts {
+  // the file as it was when these were computed; any change is a miss
+  stamp: Option<(std::time::SystemTime, u64)>,
+  table: bool,
+  lines: Vec<crate::raster::PseudoLine>,
+  circles: Vec<(f32, f32, f32)>,
+  leaders: Vec<crate::raster::Leader>,
+  runs: Vec<crate::ocr::TextRun>,
+  sheet: Option<crate::sheet::SheetId>,
+  scale: Option<f32>,
+  scale_match: Option<crate::scale::ScaleMatch>,
+}
+
+// (pdf_path, page, dpi, region as bits, circle pass on); the region masks the render, so it's part of the input
+type ArtifactKey = (String, u32, u32, Option<[u32; 4]>, bool);
+
+static ARTIFACTS: Lazy<Mutex<HashMap<ArtifactKey, Arc<PageArtifacts>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn file_stamp(path: &str) -> Option<(std::time::SystemTime, u64)> {
+  let m = std::fs::metadata(path).ok()?;
+  Some((m.modified().ok()?, m.len()))
+}
+
+fn analyze_page(ctx: &Ctx, gray: &image::GrayImage, dpi: u32, stamp: Option<(std::time::SystemTime, u64)>) -> PageArtifacts {
+  // schedules get read, not measured: no vectors or symbols from them
+  let table = crate::raster::is_table_page(gray);
+
+  // vector extraction (stubbed to empty); will read `gray`, and skip tables and quick runs
+  let lines: Vec<crate::raster::PseudoLine> = vec![];
+  // round symbols from the same pixels; radii from a couple of points up to half an inch on paper
+  let circles = if table || ctx.quick || !ctx.circles { vec![] } else {
+    let px = dpi as f32 / 72.0;
+    crate::raster::circles_on_page(gray, (CIRCLE_MIN_R_PT * px).round() as u32, (CIRCLE_MAX_R_PT * px).round() as u32)
+  };
+
+  // arrow leaders, so callout text can be tied to the symbol it points at
+  let leaders = if table || ctx.quick { vec![] } else { crate::raster::detect_leaders(gray) };
+
+  // OCR (stubbed); reads the same render so text boxes line up with the vectors
+  let runs: Vec<crate::ocr::TextRun> = vec![];
+  let ocr_text = runs.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+  let sheet = crate::sheet::parse_sheet_number(&ocr_text);
+  // a printed ratio wins; otherwise try the sheet's graphic scale bar
+  let scale_match = crate::scale::match_scale_text(&ocr_text, dpi);
+  let scale = scale_match.as_ref().map(|m| m.units_per_pixel)
+    .or_else(|| crate::scale::infer_scale_from_bar(gray, &runs).map(|g| g.units_per_pixel as f32));
+  PageArtifacts { stamp, table, lines, circles, leaders, runs, sheet, scale, scale_match }
+}
+
+// drops everything cached for the file, e.g. after it was edited; returns how many pages were held
 #[tauri::command]
-pub async fn start_auto_takeoff(pdf_path: String) -> u64 {
-  let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
-  let job = Job { id, state: JobState::Pending, result_json: None };
-  JOBS.lock().insert(id, job);
-
-  // spawn the pipeline
-  tauri::async_runtime::spawn(async move {
-    let update = |stage: &str, pct: u8| set_state(id, JobState::Running(JobProgress { stage: stage.to_string(), pct }));
-    update("open", 5);
-    // open pdf and basic info
-    let page_count = match crate::pdf::page_count_from_path(&pdf_path).await { Ok(n) => n, Err(e) => { set_state(id, JobState::Failed(e)); return; } };
-
-    // tile pyramid (stubbed)
-    update("tile-pyramid", 15);
-
-    // vector extraction (stubbed to empty)
-    update("vectors", 30);
-    let total_line_segments: usize = 0;
-
-    // OCR (stubbed)
-    update("ocr", 45);
-    let ocr_text = String::new();
-
-    // scale inference
-    update("scale", 55);
-    let inferred = crate::scale::infer_scale_from_text(ocr_text.clone());
-    let units_per_pixel = inferred.map(|(_, v)| v).unwrap_or(1.0);
-
-    // detection (stubbed)
-    update("detect", 70);
-    let detected: Vec<crate::detect::Det> = vec![];
-
-    // measurements
-    update("measure", 82);
-    let total_lineal = 0.0_f32;
-    let total_area = 0.0_f32;
-
-    // mapping
-    update("map", 92);
-    let mapping = crate::map::map_to_line_items(&detected, total_lineal as f64, total_area as f64);
-
-    // result
-    let result = json!({
-      "pdf_path": pdf_path,
-      "pages": page_count,
-      "units_per_pixel": units_per_pixel,
-      "vectors": { "segments": total_line_segments },
-      "summary": mapping.summary,
-      "items": mapping.items,
-    }).to_string();
-    set_result(id, result);
-    set_state(id, JobState::Succeeded);
-  });
+pub fn invalidate_cache(pdf_path: String) -> usize {
+  let mut cache = ARTIFACTS.lock();
+  let before = cache.len();
+  cache.retain(|k, _| k.0 != pdf_path);
+  before - cache.len()
+}
+
+// per-page stages; pages share the one pdf::bind() binding and each opens its own document.
+// pdfium-render's `thread_safe` feature serializes the calls into the library.
+fn run_page(ctx: &Ctx, pdf_path: &str, page: u32) -> Result<PageOutput, String> {
+  if ctx.cancelled() { return Err(CANCELLED.into()); }
+  let lib = crate::pdf::bind()?;
+  let doc = ctx.retry(|| lib.load_pdf_from_file(pdf_path, None).map_err(|e| e.to_string()))?;
+  let dpi = if ctx.quick { QUICK_DPI } else { crate::pdf::DEFAULT_DPI };
+
+  let key: ArtifactKey = (pdf_path.to_string(), page, dpi, ctx.region.map(|r| [r.0.to_bits(), r.1.to_bits(), r.2.to_bits(), r.3.to_bits()]), ctx.circles);
+  let stamp = file_stamp(pdf_path);
+  let cached = ARTIFACTS.lock().get(&key).filter(|a| stamp.is_some() && a.stamp == stamp).cloned();
+  let art = match cached {
+    Some(a) => { job_log(ctx.id, log::Level::Debug, format!("page={page} reusing cached artifacts")); a }
+    None => {
+      let a = Arc::new(analyze_page(ctx, &ctx.render(&doc, page, dpi)?.to_luma8(), dpi, stamp));
+      ARTIFACTS.lock().insert(key, a.clone());
+      a
+    }
+  };
+  let vectors = VectorStats::from_lines(&art.lines);
+
+  // detection on the shared bitmap; no model installed means no symbols, same as detect_symbols
+  if ctx.cancelled() { return Err(CANCELLED.into()); }
+  let model = crate::detect::model_path();
+  let mut detections = if model.exists() && !art.table { crate::detect::detect_pyramid(&model, &ctx.render(&doc, page, dpi)?, &detect_params(ctx, &model))? } else { vec![] };
+  crate::detect::attach_callouts(&mut detections, &art.leaders, &art.runs, CALLOUT_REACH_PT * dpi as f32 / 72.0);
+  let (detections, legend_hits) = match ctx.legend {
+    Some(lg) => {
+      let t = crate::transform::Transform::for_page(&doc, page, dpi)?;
+      let (a, b) = (t.point_to_pixel((lg.rect.0, lg.rect.1)), t.point_to_pixel((lg.rect.2, lg.rect.3)));
+      crate::detect::split_by_rect(detections, (a.0.min(b.0), a.1.min(b.1), a.0.max(b.0), a.1.max(b.1)))
+    }
+    None => (detections, vec![]),
+  };
+  // measurements (stubbed; traced measurements will arrive tagged by region)
+  let measurements: Vec<crate::measure::Measurement> = vec![];
+  ctx.release_page(page);
+
+  Ok(PageOutput {
+    vectors, sheet: art.sheet.clone(), scale: art.scale, scale_match: art.scale_match.clone(), detections, legend_hits, measurements,
+    runs: art.runs.clone(), lines: art.lines.clone(), circles: art.circles.clone(), table: art.table,
+  })
+}
+
+const QUICK_DPI: u32 = 72;
+const CIRCLE_MIN_R_PT: f32 = 2.0;
+const CIRCLE_MAX_R_PT: f32 = 36.0;
+// how far callout text may sit from a leader's tail, and the tip from its symbol
+const CALLOUT_REACH_PT: f32 = 12.0;
+const QUICK_MIN_SCORE: f32 = 0.5;
+
+// quick runs trade recall for speed: a single scale and only confident hits
+fn detect_params(ctx: &Ctx, model: &std::path::Path) -> crate::detect::DetectParams {
+  let mut p = crate::detect::params_for(model, None, None, None);
+  if ctx.quick {
+    p.scales = vec![1.0];
+    p.score_threshold = p.score_threshold.max(QUICK_MIN_SCORE);
+  }
+  p
+}
+
+// 0 lets rayon pick one thread per core
+fn page_pool() -> Result<rayon::ThreadPool, String> {
+  rayon::ThreadPoolBuilder::new().num_threads(crate::settings::get().page_concurrency).build().map_err(|e| e.to_string())
+}
+
+fn run_pipeline(ctx: &Ctx, pdf_path: &str, prices: &HashMap<String, f64>) -> Result<TakeoffResult, String> {
+  use rayon::prelude::*;
+  ctx.update("open", 5)?;
+  // open pdf and basic info
+  let page_count = ctx.retry(|| crate::pdf::page_count(pdf_path))?;
+  ctx.publish("pdf_path", json!(pdf_path));
+  ctx.publish("pages", json!(page_count));
+  ctx.publish("preview", json!(ctx.quick));
+  ctx.publish("region", json!(ctx.region));
+
+  // reissued sets often carry the same sheet twice; only the first copy is counted
+  let mut warnings = Vec::new();
+  let mut pages: Vec<u32> = (0..page_count).collect();
+  if crate::settings::get().skip_duplicate_pages {
+    for group in ctx.retry(|| crate::pdf::duplicate_page_groups(pdf_path))? {
+      let shown: Vec<String> = group.iter().map(|p| (p + 1).to_string()).collect();
+      warnings.push(format!("pages {} look identical; only page {} was counted", shown.join(", "), group[0] + 1));
+      pages.retain(|p| !group[1..].contains(p));
+    }
+  }
+  let todo = pages.len() as u64;
+
+  // pages run concurrently and report in as they finish; results are keyed by page so order doesn't matter
+  ctx.update("pages", 10)?;
+  *ctx.eta.lock() = Eta::new();
+  let done = AtomicU64::new(0);
+  let outputs: Mutex<std::collections::BTreeMap<u32, PageOutput>> = Mutex::new(Default::default());
+  page_pool()?.install(|| {
+    pages.into_par_iter().try_for_each(|page| {
+      let out = run_page(ctx, pdf_path, page)?;
+      outputs.lock().insert(page, out);
+      let n = done.fetch_add(1, Ordering::SeqCst) + 1;
+      let eta = Some(ctx.eta.lock().page_done(todo - n));
+      ctx.update_eta("pages", 10 + (80 * n / todo.max(1)) as u8, eta)
+    })
+  })?;
+  let outputs = outputs.into_inner();
+
+  let tables: Vec<String> = outputs.iter().filter(|(_, o)| o.table).map(|(p, _)| (p + 1).to_string()).collect();
+  if !tables.is_empty() { warnings.push(format!("page(s) {} look like schedules; symbols and lines there were not counted", tables.join(", "))); }
 
-  id
+  let mut vectors = VectorStats::default();
+  for o in outputs.values() { vectors.add(&o.vectors); }
+  ctx.publish("vectors", json!(vectors));
+  // the title block of the first identified sheet speaks for the set
+  let sheet = outputs.values().find_map(|o| o.sheet.clone());
+  ctx.publish("sheet", json!(sheet));
+
+  // scale inference: a page's override, else what it printed; the first page with either speaks for the set
+  ctx.update("scale", 90)?;
+  let own: std::collections::BTreeMap<u32, (f32, &str)> = outputs.iter().filter_map(|(&p, o)| {
+    match (ctx.page_scales.get(&p), o.scale) {
+      (Some(&upp), _) => Some((p, (upp, "override"))),
+      (None, Some(upp)) => Some((p, (upp, if o.scale_match.is_some() { "text" } else { "bar" }))),
+      (None, None) => None,
+    }
+  }).collect();
+  let units_per_pixel = own.values().next().map(|s| s.0).unwrap_or(1.0);
+  let scale_match = outputs.values().find(|o| o.scale.is_some()).and_then(|o| o.scale_match.clone());
+  let page_scales: std::collections::BTreeMap<u32, crate::scale::PageScale> = outputs.keys().map(|&p| {
+    let (upp, source) = own.get(&p).copied().unwrap_or((units_per_pixel, "document"));
+    (p, crate::scale::PageScale { units_per_pixel: upp, source: source.into() })
+  }).collect();
+  ctx.publish("units_per_pixel", json!(units_per_pixel));
+  ctx.publish("page_scales", json!(page_scales));
+  ctx.publish("scale_match", json!(scale_match));
+
+  // printed dimensions cross-check the scale; when most disagree, say what they imply instead
+  let dimension_checks: Vec<crate::scale::DimensionCheck> = outputs.iter()
+    .flat_map(|(p, o)| crate::scale::check_dimensions(&o.runs, &o.lines, page_scales[p].units_per_pixel as f64))
+    .collect();
+  let off = dimension_checks.iter().filter(|c| c.error_pct.abs() > crate::scale::DIMENSION_TOLERANCE_PCT).count();
+  if off * 2 > dimension_checks.len() {
+    let implied = crate::scale::scale_from_dimensions(&dimension_checks, units_per_pixel as f64).unwrap_or(0.0);
+    warnings.push(format!("{off} of {} printed dimensions disagree with the scale by more than {}%; they suggest {implied:.5} units per pixel", dimension_checks.len(), crate::scale::DIMENSION_TOLERANCE_PCT));
+  }
+
+  // the legend page's entries are the samples; the same symbols in that spot elsewhere are repeats of the key
+  let samples: Vec<crate::detect::LegendSymbol> = ctx.legend
+    .and_then(|lg| outputs.get(&lg.page))
+    .map(|o| o.legend_hits.iter().map(crate::detect::LegendSymbol::of).collect())
+    .unwrap_or_default();
+  let mut detected: Vec<crate::detect::Det> = vec![];
+  let mut measurements: Vec<crate::measure::Measurement> = vec![];
+  let mut in_legend = 0;
+  let circles: usize = outputs.values().map(|o| o.circles.len()).sum();
+  for (page, o) in outputs.into_iter() {
+    detected.extend(o.detections);
+    let before = detected.len();
+    if ctx.legend.is_some_and(|lg| lg.page != page) {
+      detected.extend(o.legend_hits.iter().filter(|d| !samples.iter().any(|s| s.matches(d))).cloned());
+    }
+    in_legend += o.legend_hits.len() - (detected.len() - before);
+    let upp = page_scales[&page].units_per_pixel as f64;
+    measurements.extend(o.measurements.into_iter().map(|m| crate::measure::Measurement { units_per_pixel: Some(m.scale_or(upp)), ..m }));
+  }
+  if in_legend > 0 { warnings.push(format!("{in_legend} symbol(s) inside the legend area were not counted")); }
+  ctx.publish("detections", json!(detected));
+
+  // mapping
+  ctx.update("map", 95)?;
+  let report = crate::map::map_by_region(&detected, &measurements, units_per_pixel as f64, sheet.as_ref().map(|s| s.discipline), prices);
+  let mut mapping = report.total;
+  mapping.summary.circles = circles;
+  warnings.extend(mapping.warnings);
+
+  Ok(TakeoffResult {
+    pdf_path: pdf_path.to_string(),
+    pages: page_count,
+    units_per_pixel,
+    vectors,
+    summary: mapping.summary,
+    items: mapping.items,
+    unmapped: mapping.unmapped,
+    partial: false,
+    sheet,
+    warnings,
+    regions: report.regions,
+    dimension_checks,
+    preview: ctx.quick,
+    region: ctx.region,
+    page_scales,
+    scale_match,
+  })
+}
+
+// drop the finished result into the configured output folder and tell the owning window where it went
+fn write_output(ctx: &Ctx, result: &TakeoffResult, json_text: &str) -> Result<(), String> {
+  // previews are throwaway; only full runs land in the output folder
+  if result.preview { return Ok(()); }
+  let Some(dir) = crate::settings::get().output_dir else { return Ok(()) };
+  std::fs::create_dir_all(&dir).map_err(|e| format!("{dir}: {e}"))?;
+  let stem = match &result.sheet {
+    Some(sheet) => sheet.file_stem(),
+    None => std::path::Path::new(&result.pdf_path).file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| format!("job{}", ctx.id)),
+  };
+  let path = std::path::Path::new(&dir).join(format!("{stem}_takeoff.json"));
+  std::fs::write(&path, json_text).map_err(|e| format!("{}: {e}", path.display()))?;
+  let _ = ctx.app.emit_to(&ctx.owner, "job:output_ready", json!({
+    "id": ctx.id, "dir": dir, "files": [path.to_string_lossy()],
+  }));
+  Ok(())
+}
+
+// a job waiting for a dispatch slot
+struct Queued {
+  app: AppHandle,
+  owner: String,
+  id: u64,
+  cancel: Arc<AtomicBool>,
+  pdf_path: String,
+  prices: HashMap<String, f64>,
+  quick: bool,
+  region: Option<(f32, f32, f32, f32)>,
+}
+
+static QUEUE: Lazy<Mutex<std::collections::VecDeque<Queued>>> = Lazy::new(|| Mutex::new(Default::default()));
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static RUNNING: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+// set once quitting starts; no new jobs are accepted after it
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+// start queued jobs until the concurrency limit is reached; called on enqueue, on resume and as jobs finish
+fn dispatch() {
+  let max = crate::settings::get().max_jobs.max(1);
+  loop {
+    // the slot check and the increment happen under the queue lock so concurrent dispatches can't overshoot
+    let q = {
+      let mut queue = QUEUE.lock();
+      if PAUSED.load(Ordering::SeqCst) || RUNNING.load(Ordering::SeqCst) >= max { return; }
+      let Some(q) = queue.pop_front() else { return };
+      RUNNING.fetch_add(1, Ordering::SeqCst);
+      q
+    };
+    // the pipeline is blocking work (pdfium, backoff sleeps), keep it off the async workers
+    tauri::async_runtime::spawn_blocking(move || {
+      run_job(q);
+      RUNNING.fetch_sub(1, Ordering::SeqCst);
+      dispatch();
+    });
+  }
+}
+
+fn run_job(q: Queued) {
+  let Queued { app, owner, id, cancel, pdf_path, prices, quick, region } = q;
+  let started = std::time::Instant::now();
+  job_log(id, log::Level::Info, format!("start pdf={pdf_path:?}"));
+  let ctx = Ctx { app, owner, id, cancel, retries: crate::settings::get().stage_retries, quick, circles: crate::settings::get().circle_detection, region, legend: crate::detect::legend(&pdf_path), page_scales: crate::scale::page_scale_overrides(&pdf_path, if quick { QUICK_DPI } else { crate::pdf::DEFAULT_DPI }), partial: Mutex::new(serde_json::Map::new()), eta: Mutex::new(Eta::new()), renders: Mutex::new(HashMap::new()) };
+  match run_pipeline(&ctx, &pdf_path, &prices) {
+    Ok(result) => {
+      job_log(id, log::Level::Info, format!("succeeded elapsed_ms={} items={}", started.elapsed().as_millis(), result.items.len()));
+      let text = serde_json::to_string(&result).unwrap_or_else(|_| "{}".into());
+      // a bad output folder shouldn't fail an otherwise good takeoff
+      if let Err(e) = write_output(&ctx, &result, &text) { job_log(id, log::Level::Warn, format!("writing output: {e}")); }
+      set_result(id, text);
+      set_state(id, JobState::Succeeded);
+      emit_progress(&ctx.app, &ctx.owner, id, "done", 100, None);
+    }
+    Err(_) if ctx.cancelled() => {
+      job_log(id, log::Level::Info, format!("cancelled elapsed_ms={}", started.elapsed().as_millis()));
+      set_state(id, JobState::Cancelled);
+      emit_progress(&ctx.app, &ctx.owner, id, "cancelled", 100, None);
+    }
+    Err(e) => {
+      job_log(id, log::Level::Error, format!("failed elapsed_ms={} error={e:?}", started.elapsed().as_millis()));
+      set_state(id, JobState::Failed(e));
+      emit_progress(&ctx.app, &ctx.owner, id, "failed", 100, None);
+    }
+  }
+  CANCEL.lock().remove(&id);
+  persist();
+}
+
+// `prev_result_json`: an earlier result whose unit prices carry forward (see map::import_price_overrides).
+// `quick`: a fast approximate pass for sanity-checking a sheet; the result is flagged `preview`.
+// `job_id`: use this id instead of the next free one (tests, scripted runs); fails if it's taken.
+// `region`: (x0, y0, x1, y1) in page points; only what lies inside is detected, vectorized and measured.
+#[tauri::command]
+pub async fn start_auto_takeoff(app: AppHandle, window: tauri::Window, pdf_path: String, prev_result_json: Option<String>, quick: Option<bool>, job_id: Option<u64>, region: Option<(f32, f32, f32, f32)>) -> Result<u64, String> {
+  if SHUTTING_DOWN.load(Ordering::SeqCst) { return Err("shutting_down: the app is quitting".into()); }
+  // fail before queueing rather than halfway through the pipeline
+  let check = crate::pdf::validate(&pdf_path)?;
+  if check.encrypted { return Err(format!("{pdf_path} is password protected")); }
+  if check.pages == 0 { return Err(format!("{}: {pdf_path} has no pages", crate::pdf::EMPTY_DOCUMENT)); }
+  if let Some((x0, y0, x1, y1)) = region {
+    if ![x0, y0, x1, y1].iter().all(|v| v.is_finite()) || x1 <= x0 || y1 <= y0 { return Err(format!("invalid region ({x0},{y0})-({x1},{y1})")); }
+  }
+  let prices = match prev_result_json { Some(j) => crate::map::import_price_overrides(j)?, None => HashMap::new() };
+  let owner = window.label().to_string();
+  let id = claim_id(job_id, &owner)?;
+  let cancel = Arc::new(AtomicBool::new(false));
+  CANCEL.lock().insert(id, cancel.clone());
+  QUEUE.lock().push_back(Queued { app, owner, id, cancel, pdf_path, prices, quick: quick.unwrap_or(false), region });
+  dispatch();
+  Ok(id)
+}
+
+// a job still in the queue is finished on the spot; a running one stops at its next stage boundary
+fn cancel(id: u64) -> bool {
+  let Some(flag) = CANCEL.lock().get(&id).cloned() else { return false };
+  flag.store(true, Ordering::SeqCst);
+  let queued = {
+    let mut queue = QUEUE.lock();
+    queue.iter().position(|q| q.id == id).and_then(|i| queue.remove(i))
+  };
+  if let Some(q) = queued {
+    job_log(id, log::Level::Info, "cancelled before start".into());
+    set_state(id, JobState::Cancelled);
+    emit_progress(&q.app, &q.owner, id, "cancelled", 100, None);
+    CANCEL.lock().remove(&id);
+    persist();
+  }
+  true
+}
+
+#[tauri::command]
+pub async fn cancel_job(id: u64) -> bool { cancel(id) }
+
+// returns how many jobs were signalled
+#[tauri::command]
+pub async fn cancel_all_jobs() -> usize {
+  let ids: Vec<u64> = CANCEL.lock().keys().copied().collect();
+  ids.into_iter().filter(|&id| cancel(id)).count()
+}
+
+// for quitting: refuse new jobs, cancel everything, give running pipelines up to `wait` to reach a stage
+// boundary, then write the job store. Returns how many were still running when the wait ran out.
+pub fn shutdown(wait: std::time::Duration) -> usize {
+  SHUTTING_DOWN.store(true, Ordering::SeqCst);
+  PAUSED.store(true, Ordering::SeqCst);
+  let ids: Vec<u64> = CANCEL.lock().keys().copied().collect();
+  for id in ids { cancel(id); }
+  let deadline = std::time::Instant::now() + wait;
+  while RUNNING.load(Ordering::SeqCst) > 0 && std::time::Instant::now() < deadline {
+    std::thread::sleep(std::time::Duration::from_millis(50));
+  }
+  let left = RUNNING.load(Ordering::SeqCst);
+  if left > 0 { log::warn!(target: "jobs", "quitting with {left} job(s) still stopping"); }
+  persist();
+  left
+}
+
+// stops dispatching queued jobs; running ones carry on
+#[tauri::command]
+pub async fn pause_queue() { PAUSED.store(true, Ordering::SeqCst); }
+
+#[tauri::command]
+pub async fn resume_queue() {
+  PAUSED.store(false, Ordering::SeqCst);
+  dispatch();
+}
+
+#[tauri::command]
+pub async fn list_jobs() -> serde_json::Value {
+  let mut jobs: Vec<serde_json::Value> = JOBS.lock().values()
+    .map(|j| json!({ "id": j.id, "state": j.state, "owner": j.owner }))
+    .collect();
+  jobs.sort_by_key(|j| j["id"].as_u64());
+  json!({ "paused": PAUSED.load(Ordering::SeqCst), "queued": QUEUE.lock().len(), "running": RUNNING.load(Ordering::SeqCst), "jobs": jobs })
 }
 
 #[tauri::command]
@@ -101,9 +774,68 @@ pub async fn job_status(id: u64) -> serde_json::Value {
   }
 }
 
+// safe mid-run: returns whatever stages have published so far, flagged `partial: true`
 #[tauri::command]
 pub async fn job_result(id: u64) -> String {
   JOBS.lock().get(&id).and_then(|j| j.result_json.clone()).unwrap_or_else(|| "{}".into())
 }
 
 
+
+// None until the job has a complete result
+#[tauri::command]
+pub async fn job_result_typed(id: u64) -> Option<TakeoffResult> {
+  let json = JOBS.lock().get(&id).and_then(|j| j.result_json.clone())?;
+  serde_json::from_str(&json).ok()
+}
+
+// everything a maintainer needs to triage a failed run, built only from what the job already holds.
+// With privacy mode on, absolute paths are cut down to the file name.
+#[tauri::command]
+pub async fn export_error_report(id: u64, out_dir: String) -> Result<String, String> {
+  let job = JOBS.lock().get(&id).cloned().ok_or_else(|| format!("no job {id}"))?;
+  let result: serde_json::Value = job.result_json.as_deref().and_then(|j| serde_json::from_str(j).ok()).unwrap_or(json!({}));
+  let pdf_path = result["pdf_path"].as_str().unwrap_or_default().to_string();
+  let shown = if crate::settings::get().privacy_mode {
+    std::path::Path::new(&pdf_path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+  } else {
+    pdf_path.clone()
+  };
+  let log: Vec<String> = job.log.iter()
+    .map(|l| if pdf_path.is_empty() || shown == pdf_path { l.clone() } else { l.replace(&pdf_path, &shown) })
+    .collect();
+  let error = match &job.state { JobState::Failed(e) => Some(e.clone()), _ => None };
+  let report = json!({
+    "id": id,
+    "version": env!("CARGO_PKG_VERSION"),
+    "state": job.state,
+    "error": error,
+    "pdf": shown,
+    "pages": result.get("pages"),
+    "units_per_pixel": result.get("units_per_pixel"),
+    "log": log,
+  });
+  std::fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+  let path = std::path::Path::new(&out_dir).join(format!("report_{id}.json"));
+  std::fs::write(&path, serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+  Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ids_count_from_one_after_a_reset() {
+    // local state, so this neither races other tests nor touches the running app's jobs
+    let (mut jobs, next) = (HashMap::new(), AtomicU64::new(1));
+    assert_eq!(claim_in(&mut jobs, &next, None, "main"), Ok(1));
+    assert_eq!(claim_in(&mut jobs, &next, None, "main"), Ok(2));
+    assert!(claim_in(&mut jobs, &next, Some(2), "main").is_err());
+    assert!(claim_in(&mut jobs, &next, Some(0), "main").is_err());
+    // what reset_job_counter does; ids still held are skipped rather than handed out twice
+    next.store(1, Ordering::SeqCst);
+    assert_eq!(claim_in(&mut jobs, &next, None, "main"), Ok(3));
+    assert_eq!(jobs.len(), 3);
+  }
+}
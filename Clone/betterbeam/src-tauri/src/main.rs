@@ -1,15 +1,24 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::{Manager, menu::{Menu, Submenu, MenuItem}, tray::{SystemTray, SystemTrayMenu, SystemTrayEvent}};
+use tauri::{Emitter, Listener, Manager, menu::{Menu, Submenu, MenuItem}, tray::{SystemTray, SystemTrayMenu, SystemTrayEvent}};
 
 mod jobs;
 mod pdf;
-mod ocr; // placeholder (frontend uses tesseract.js)
+mod ocr; // engine is a placeholder (frontend uses tesseract.js)
 mod detect;
 mod scale;
 mod measure;
 mod map;
 mod raster;
+mod vector;
+mod settings;
+mod overlay;
+mod export;
+mod tiles;
+mod sheet;
+mod transform;
+mod events;
+mod diagnostics;
 
 #[tauri::command]
 fn open_in_explorer(path: String) -> Result<(), String> {
@@ -23,20 +32,69 @@ fn open_in_explorer(path: String) -> Result<(), String> {
   }
 }
 
+static COMPARE_SEQ: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(1);
+static LAST_FOCUSED: parking_lot::Mutex<String> = parking_lot::Mutex::new(String::new());
+
+// a second, independent viewer; the sheet to load is handed over as window.__COMPARE__
+#[tauri::command]
+fn open_compare_window(app: tauri::AppHandle, pdf_path: String, page: u32) -> Result<String, String> {
+  let label = format!("compare-{}", COMPARE_SEQ.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+  let init = serde_json::json!({ "pdf_path": pdf_path, "page": page });
+  let name = std::path::Path::new(&pdf_path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+  tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::App("index.html".into()))
+    .title(format!("BetterBeam – {name} (p. {})", page + 1))
+    .inner_size(1280.0, 800.0)
+    .initialization_script(&format!("window.__COMPARE__ = {init};"))
+    .build()
+    .map_err(|e| e.to_string())?;
+  Ok(label)
+}
+
+// a window saved on a monitor that's since been unplugged reopens off-screen; this forgets the saved
+// geometry and puts main back in the middle of the primary monitor at the default size
+#[tauri::command]
+fn reset_window_state(app: tauri::AppHandle) -> Result<(), String> {
+  // the file tauri_plugin_window_state keeps in the config dir
+  if let Ok(dir) = app.path().app_config_dir() {
+    let file = dir.join(".window-state.json");
+    if file.exists() { std::fs::remove_file(&file).map_err(|e| e.to_string())?; }
+  }
+  let w = app.get_window("main").ok_or("main window not found")?;
+  let _ = w.unmaximize();
+  let size = tauri::PhysicalSize::new(1280u32, 800u32);
+  w.set_size(size).map_err(|e| e.to_string())?;
+  match w.primary_monitor().map_err(|e| e.to_string())? {
+    Some(m) => {
+      let (mp, ms) = (m.position(), m.size());
+      let x = mp.x + (ms.width as i32 - size.width as i32).max(0) / 2;
+      let y = mp.y + (ms.height as i32 - size.height as i32).max(0) / 2;
+      w.set_position(tauri::PhysicalPosition::new(x, y)).map_err(|e| e.to_string())?;
+    }
+    None => w.center().map_err(|e| e.to_string())?,
+  }
+  let _ = w.show();
+  w.set_focus().map_err(|e| e.to_string())
+}
+
+// accelerators go through the menu, so shortcuts hit the same on_menu_event handlers as clicks;
+// CmdOrCtrl resolves to Cmd on macOS
 fn make_menu() -> Menu {
   let file = Submenu::new("File", Menu::with_items([
-    MenuItem::new("open", "Open…", true, None),
-    MenuItem::new("save_as", "Save As…", true, None),
+    MenuItem::new("open", "Open…", true, Some("CmdOrCtrl+O")),
+    MenuItem::new("save_as", "Save As…", true, Some("CmdOrCtrl+S")),
+    MenuItem::new("print", "Print…", true, None),
     MenuItem::separator(),
     MenuItem::new("quit", "Quit", true, None),
   ]));
 
   let view = Submenu::new("View", Menu::with_items([
-    MenuItem::new("reload", "Reload", true, None),
-    MenuItem::new("toggle_devtools", "Toggle DevTools", true, None),
+    MenuItem::new("reload", "Reload", true, Some("CmdOrCtrl+R")),
+    MenuItem::new("toggle_devtools", "Toggle DevTools", true, Some("F12")),
+    MenuItem::new("toggle_theme", "Toggle Theme", true, None),
   ]));
 
   let help = Submenu::new("Help", Menu::with_items([
+    MenuItem::new("reset_window", "Reset Window Position", true, None),
     MenuItem::new("about", "About", true, None),
   ]));
 
@@ -53,6 +111,41 @@ fn make_tray() -> SystemTray {
   SystemTray::new().with_menu(tray_menu)
 }
 
+fn apply_theme(app: &tauri::AppHandle, theme: settings::Theme) {
+  if let Some(w) = app.get_window("main") {
+    let native = match theme { settings::Theme::Light => tauri::Theme::Light, settings::Theme::Dark => tauri::Theme::Dark };
+    // not every platform/webview supports a native theme; the frontend still gets the event
+    let _ = w.set_theme(Some(native));
+  }
+  let _ = app.emit("theme:changed", theme);
+}
+
+// mirror job:progress in the taskbar; platforms without a progress indicator just ignore it
+fn reflect_progress(app: &tauri::AppHandle, payload: &str) {
+  use tauri::window::{ProgressBarState, ProgressBarStatus};
+  let Ok(v) = serde_json::from_str::<serde_json::Value>(payload) else { return };
+  let Some(w) = app.get_window(v["window"].as_str().unwrap_or("main")) else { return };
+  let state = match v["stage"].as_str() {
+    Some("done") | Some("failed") | Some("cancelled") => ProgressBarState { status: Some(ProgressBarStatus::None), progress: None },
+    _ if v["indeterminate"].as_bool() == Some(true) => ProgressBarState { status: Some(ProgressBarStatus::Indeterminate), progress: None },
+    _ => ProgressBarState { status: Some(ProgressBarStatus::Normal), progress: v["pct"].as_u64() },
+  };
+  let _ = w.set_progress_bar(state);
+}
+
+// longest quitting waits on running jobs before exiting anyway
+const SHUTDOWN_WAIT: std::time::Duration = std::time::Duration::from_secs(3);
+
+// cancel and flush before exiting, so the job store isn't cut off mid-write. Settings, OCR sidecars
+// and catalogs are written as they change and have nothing pending; tiles are memory-only.
+fn quit(app: &tauri::AppHandle) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    jobs::shutdown(SHUTDOWN_WAIT);
+    app.exit(0);
+  });
+}
+
 fn main() {
   let menu = make_menu();
   let tray = make_tray();
@@ -60,16 +153,38 @@ fn main() {
   tauri::Builder::default()
     // Persist window size/position
     .plugin(tauri_plugin_window_state::Builder::default().build())
-    // Logging (file & console)
-    .plugin(tauri_plugin_log::Builder::default().build())
+    // Logging (file & console); filtered at runtime via log::set_max_level, see settings::set_log_level
+    .plugin(tauri_plugin_log::Builder::default().level(log::LevelFilter::Trace).build())
     // FS + Dialog + Shell
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_shell::init())
     // Single instance
     .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-      if let Some(w) = app.get_window("main") { let _ = w.unminimize(); let _ = w.show(); let _ = w.set_focus(); }
+      // bring back whichever window the user last worked in, not always the main one
+      let last = LAST_FOCUSED.lock().clone();
+      if let Some(w) = app.get_window(&last).or_else(|| app.get_window("main")) { let _ = w.unminimize(); let _ = w.show(); let _ = w.set_focus(); }
     }))
+    .on_window_event(|w, e| {
+      match e {
+        tauri::WindowEvent::Focused(true) => *LAST_FOCUSED.lock() = w.label().to_string(),
+        // moved to a monitor with a different scale factor: the frontend re-requests tiles and renders
+        tauri::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+          let _ = w.emit_to(w.label(), "display:scale_changed", serde_json::json!({ "window": w.label(), "scale_factor": scale_factor }));
+        }
+        _ => {}
+      }
+    })
+    .setup(|app| {
+      settings::init(app.handle());
+      jobs::init(app.handle());
+      map::watch_catalogs(app.handle().clone());
+      log::set_max_level(settings::parse_level(&settings::get().log_level).unwrap_or(log::LevelFilter::Info));
+      apply_theme(app.handle(), settings::get().theme);
+      let handle = app.handle().clone();
+      app.listen_any("job:progress", move |e| reflect_progress(&handle, e.payload()));
+      Ok(())
+    })
     // UI chrome
     .menu(menu)
     .on_menu_event(|app, e| {
@@ -92,14 +207,22 @@ fn main() {
             }
           });
         }
+        // the frontend owns the overlay state, so it answers with print_page
+        "print" => { let _ = app.emit("file:print", ()); }
         "reload" => { if let Some(w) = app.get_window("main") { let _ = w.eval("location.reload()"); } }
         "toggle_devtools" => { if let Some(w) = app.get_window("main") { let _ = w.open_devtools(); } }
+        "toggle_theme" => {
+          let next = match settings::get().theme { settings::Theme::Light => settings::Theme::Dark, settings::Theme::Dark => settings::Theme::Light };
+          if let Err(e) = settings::update(|s| s.theme = next) { log::warn!("saving theme: {e}"); }
+          apply_theme(app, next);
+        }
+        "reset_window" => { if let Err(e) = reset_window_state(app.clone()) { log::warn!("resetting window state: {e}"); } }
         "about" => {
           let _ = tauri_plugin_dialog::MessageDialogBuilder::new("BetterBeam", "BetterBeam\n© YourCo")
             .kind(tauri_plugin_dialog::MessageDialogKind::Info)
             .show(|_| {});
         }
-        "quit" => app.exit(0),
+        "quit" => quit(app),
         _ => {}
       }
     })
@@ -108,49 +231,86 @@ fn main() {
       SystemTrayEvent::MenuItemClick { id, .. } => match id.as_ref() {
         "show" => { if let Some(w) = app.get_window("main") { let _ = w.show(); let _ = w.set_focus(); } }
         "hide" => { if let Some(w) = app.get_window("main") { let _ = w.hide(); } }
-        "quit" => app.exit(0),
+        "quit" => quit(app),
         _ => {}
       },
       _ => {}
     })
     .invoke_handler(tauri::generate_handler![
       open_in_explorer,
+      diagnostics::diagnostics,
       jobs::start_auto_takeoff,
       jobs::job_status,
       jobs::job_result,
+      jobs::job_result_typed,
+      jobs::cancel_job,
+      jobs::cancel_all_jobs,
+      jobs::pause_queue,
+      jobs::resume_queue,
+      jobs::list_jobs,
+      jobs::invalidate_cache,
+      jobs::reset_job_counter,
+      jobs::export_error_report,
+      pdf::page_effective_dpi,
+      pdf::validate_pdf,
+      pdf::render_page_image,
+      pdf::find_duplicate_pages,
+      transform::page_transform,
+      transform::display_scale,
+      sheet::count_pages_by_discipline,
+      ocr::ocr_document,
+      ocr::search_text,
+      ocr::page_text_index,
       detect::detect_symbols,
-      prefetch_view
+      detect::detect_symbols_file,
+      detect::detect_symbols_page,
+      detect::detect_region,
+      detect::render_detection_heatmap,
+      detect::detection_score_histogram,
+      detect::evaluate_detection,
+      detect::cluster_symbols,
+      detect::set_legend_region,
+      detect::select_model,
+      detect::set_model_defaults,
+      detect::reset_model_defaults,
+      settings::get_settings,
+      settings::set_log_level,
+      settings::set_output_dir,
+      map::validate_catalog_file,
+      map::reload_catalog,
+      map::import_price_overrides,
+      measure::snap_points,
+      scale::verify_scale,
+      scale::set_page_scale,
+      scale::confirm_scale,
+      scale::recent_scales,
+      scale::apply_recent_scale,
+      vector::set_snap_lines,
+      vector::snap_to_lines,
+      vector::page_grid,
+      vector::grid_reference,
+      vector::room_outlines,
+      raster::benchmark_vectorize,
+      raster::hatch_area_seeds,
+      raster::vectorize_color_layer,
+      measure::measure_length,
+      measure::measure_area,
+      measure::wall_area,
+      measure::compute_measurement,
+      export::print_page,
+      overlay::render_detection_overlay,
+      overlay::get_label_styles,
+      overlay::set_label_color,
+      export::export_quote_pdf,
+      export::export_annotations,
+      export::export_all_overlays,
+      open_compare_window,
+      reset_window_state,
+      tiles::prefetch_view,
+      tiles::get_tile,
+      tiles::evict_tiles,
+      tiles::set_tile_cache_budget
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
-
-#[tauri::command]
-fn prefetch_view(pdf_path:String, page:u32, dpi:u32, x0:f32, y0:f32, x1:f32, y1:f32, tile:f32) -> Result<(), String> {
-  std::thread::spawn(move || {
-    let pdfium = match pdfium_render::prelude::Pdfium::new(
-      pdfium_render::prelude::Pdfium::bind_to_system_library()
-        .or_else(|_| pdfium_render::prelude::Pdfium::bind_to_builtin_library())
-    ) {
-      Ok(p) => p, Err(_) => return,
-    };
-    let doc = match pdfium.load_pdf_from_file(&pdf_path, None) { Ok(d) => d, Err(_) => return };
-    let overlap = 64.0;
-    let tile = tile.max(256.0).min(1024.0);
-    let step = (tile - overlap).max(256.0);
-    let mut ty = y0;
-    while ty < y1 {
-      let mut tx = x0;
-      while tx < x1 {
-        let tw = tile.min(x1 - tx).max(0.0);
-        let th = tile.min(y1 - ty).max(0.0);
-        let _ = (|| -> Result<(), String> { let _ = (tx, ty, tw, th, dpi, page); Ok(()) })();
-        tx += step;
-      }
-      ty += step;
-    }
-  });
-  Ok(())
-}
-
-
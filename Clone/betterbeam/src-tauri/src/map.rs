@@ -1,10 +1,79 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct MappingSummary {
   pub symbols: serde_json::Value,
   pub lineal_feet: f64,
+  // share of lineal_feet per measurement label; unlabeled runs only count toward the total
+  #[serde(default)]
+  pub lineal_by_label: BTreeMap<String, f64>,
   pub area_sqft: f64,
+  // round features found in the drawing (columns, manholes, penetrations); counted, not priced
+  #[serde(default)]
+  pub circles: usize,
+  // cost roll-up, each step kept separately so the math can be checked: see `Pricing`
+  #[serde(default)]
+  pub subtotal: f64,
+  #[serde(default)]
+  pub overhead: f64,
+  #[serde(default)]
+  pub profit: f64,
+  #[serde(default)]
+  pub tax: f64,
+  #[serde(default)]
+  pub total: f64,
+  #[serde(default)]
+  pub currency: String,
+}
+
+// applied in order: overhead on the subtotal, profit on subtotal + overhead, tax on everything before it
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Pricing {
+  pub overhead_pct: f64,
+  pub profit_pct: f64,
+  pub tax_pct: f64,
+  pub currency: String,
+}
+
+impl Default for Pricing {
+  fn default() -> Self { Pricing { overhead_pct: 0.0, profit_pct: 0.0, tax_pct: 0.0, currency: "USD".into() } }
+}
+
+fn round_cents(v: f64) -> f64 { (v * 100.0).round() / 100.0 }
+
+impl MappingSummary {
+  pub fn apply_pricing(&mut self, items: &[LineItem], p: &Pricing) {
+    self.subtotal = round_cents(items.iter().map(|i| i.qty as f64 * i.unit_price).sum());
+    self.overhead = round_cents(self.subtotal * p.overhead_pct / 100.0);
+    self.profit = round_cents((self.subtotal + self.overhead) * p.profit_pct / 100.0);
+    self.tax = round_cents((self.subtotal + self.overhead + self.profit) * p.tax_pct / 100.0);
+    self.total = round_cents(self.subtotal + self.overhead + self.profit + self.tax);
+    self.currency = p.currency.clone();
+  }
+}
+
+// "$1,234.50"; codes without a known symbol print as "CHF 1,234.50"
+pub fn format_money(v: f64, currency: &str) -> String {
+  let cents = (v.abs() * 100.0).round() as u64;
+  let digits = (cents / 100).to_string();
+  let mut whole = String::new();
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i) % 3 == 0 { whole.push(','); }
+    whole.push(c);
+  }
+  let sign = if v < 0.0 && cents > 0 { "-" } else { "" };
+  let amount = format!("{whole}.{:02}", cents % 100);
+  match currency.to_ascii_uppercase().as_str() {
+    "USD" | "" => format!("{sign}${amount}"),
+    "CAD" => format!("{sign}CA${amount}"),
+    "AUD" => format!("{sign}A${amount}"),
+    "EUR" => format!("{sign}€{amount}"),
+    "GBP" => format!("{sign}£{amount}"),
+    code => format!("{sign}{code} {amount}"),
+  }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -13,23 +82,338 @@ pub struct LineItem {
   pub qty: u32,
   pub material: String,
   pub finish: String,
+  #[serde(default)]
+  pub unit_price: f64,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct MappingResult {
   pub summary: MappingSummary,
   pub items: Vec<LineItem>,
+  // e.g. catalog problems; mapping still runs with whatever could be used
+  #[serde(default)]
+  pub warnings: Vec<String>,
+  // detected labels with no catalog row: counted in the summary, missing from items
+  #[serde(default)]
+  pub unmapped: Vec<UnmappedLabel>,
 }
 
-pub fn map_to_line_items(_dets: &Vec<crate::detect::Det>, lineal: f64, area: f64) -> MappingResult {
-  MappingResult {
-    summary: MappingSummary {
-      symbols: serde_json::json!({}),
-      lineal_feet: lineal,
-      area_sqft: area,
-    },
-    items: vec![],
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct UnmappedLabel { pub label: String, pub count: u32 }
+
+fn one() -> f64 { 1.0 }
+
+// one catalog row: which part a detected label turns into, and how many per detection
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CatalogEntry {
+  pub label: String,
+  pub sku: String,
+  pub material: String,
+  pub finish: String,
+  #[serde(default = "one")]
+  pub qty_per: f64,
+  #[serde(default)]
+  pub unit_price: f64,
+}
+
+pub type Catalog = Vec<CatalogEntry>;
+
+pub fn catalog_dir() -> PathBuf { Path::new("src-tauri").join("catalogs") }
+
+fn discipline_file(d: char) -> Option<&'static str> {
+  match d.to_ascii_uppercase() {
+    'A' => Some("architectural.json"),
+    'C' => Some("civil.json"),
+    'E' => Some("electrical.json"),
+    'F' => Some("fire.json"),
+    'M' => Some("mechanical.json"),
+    'P' => Some("plumbing.json"),
+    'S' => Some("structural.json"),
+    _ => None,
+  }
+}
+
+// discipline catalog when one exists for the letter, otherwise default.json
+pub fn resolve_catalog_path(discipline: Option<char>) -> PathBuf {
+  let dir = catalog_dir();
+  if let Some(p) = discipline.and_then(discipline_file).map(|f| dir.join(f)) {
+    if p.exists() { return p; }
+  }
+  dir.join("default.json")
+}
+
+// checks every entry instead of stopping at the first problem, so a hand-edited file can be fixed in one go
+pub fn validate_catalog(value: &serde_json::Value) -> Result<(), Vec<String>> {
+  let Some(entries) = value.as_array() else { return Err(vec!["catalog must be a JSON array of entries".into()]) };
+  let mut errs = Vec::new();
+  let mut labels: BTreeMap<&str, usize> = BTreeMap::new();
+  for (i, e) in entries.iter().enumerate() {
+    let Some(obj) = e.as_object() else { errs.push(format!("entry {i}: not an object")); continue };
+    for key in ["label", "sku", "material", "finish"] {
+      match obj.get(key) {
+        None => errs.push(format!("entry {i}: missing `{key}`")),
+        Some(v) if !v.is_string() => errs.push(format!("entry {i}: `{key}` must be a string")),
+        Some(v) if v.as_str().is_some_and(|s| s.trim().is_empty()) => errs.push(format!("entry {i}: `{key}` is empty")),
+        _ => {}
+      }
+    }
+    match obj.get("qty_per") {
+      None => errs.push(format!("entry {i}: missing `qty_per`")),
+      Some(v) => match v.as_f64() {
+        Some(q) if q > 0.0 => {}
+        Some(_) => errs.push(format!("entry {i}: `qty_per` must be positive")),
+        None => errs.push(format!("entry {i}: `qty_per` must be a number")),
+      },
+    }
+    if obj.get("unit_price").is_some_and(|v| v.as_f64().is_none_or(|p| p < 0.0)) {
+      errs.push(format!("entry {i}: `unit_price` must be a non-negative number"));
+    }
+    if let Some(label) = obj.get("label").and_then(|v| v.as_str()) {
+      if let Some(first) = labels.insert(label, i) { errs.push(format!("entry {i}: duplicate label `{label}` (also entry {first})")); }
+    }
   }
+  if errs.is_empty() { Ok(()) } else { Err(errs) }
 }
 
+pub fn load_catalog(path: &Path) -> Result<Catalog, Vec<String>> {
+  let text = std::fs::read_to_string(path).map_err(|e| vec![format!("{}: {e}", path.display())])?;
+  let value: serde_json::Value = serde_json::from_str(&text).map_err(|e| vec![format!("{}: {e}", path.display())])?;
+  validate_catalog(&value).map_err(|errs| errs.into_iter().map(|e| format!("{}: {e}", path.display())).collect::<Vec<_>>())?;
+  serde_json::from_value(value).map_err(|e| vec![format!("{}: {e}", path.display())])
+}
 
+// parsed catalogs by path; mapping reads these instead of the disk so a bad edit mid-session
+// can't take pricing down. Filled on first use, refreshed by reload.
+static CATALOGS: once_cell::sync::Lazy<parking_lot::Mutex<HashMap<PathBuf, Catalog>>> = once_cell::sync::Lazy::new(Default::default);
+
+// the in-memory catalog for `path`, loading it the first time; errors only when there is no good copy to fall back on
+pub fn cached_catalog(path: &Path) -> Result<Catalog, Vec<String>> {
+  if let Some(c) = CATALOGS.lock().get(path) { return Ok(c.clone()); }
+  let c = load_catalog(path)?;
+  CATALOGS.lock().insert(path.to_path_buf(), c.clone());
+  Ok(c)
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct CatalogReload {
+  pub reloaded: Vec<String>,
+  // files that failed validation; their previous copy stays in use
+  pub errors: Vec<String>,
+}
+
+fn reload_paths(paths: impl IntoIterator<Item = PathBuf>) -> CatalogReload {
+  let mut out = CatalogReload::default();
+  for p in paths {
+    match load_catalog(&p) {
+      Ok(c) => { CATALOGS.lock().insert(p.clone(), c); out.reloaded.push(p.to_string_lossy().to_string()); }
+      Err(errs) => out.errors.extend(errs),
+    }
+  }
+  out
+}
+
+fn catalog_files() -> Vec<PathBuf> {
+  let Ok(rd) = std::fs::read_dir(catalog_dir()) else { return vec![] };
+  let mut files: Vec<PathBuf> = rd.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "json")).collect();
+  files.sort();
+  files
+}
+
+// re-reads every catalog in catalog_dir() so edits apply to the next mapping without a restart
+#[tauri::command]
+pub fn reload_catalog() -> CatalogReload {
+  reload_paths(catalog_files())
+}
+
+const CATALOG_POLL: std::time::Duration = std::time::Duration::from_secs(2);
+
+// polls catalog_dir() for changed files and reloads them, announcing each round on `catalog:reloaded`
+pub fn watch_catalogs(app: tauri::AppHandle) {
+  std::thread::spawn(move || {
+    let stamp = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let mut seen: HashMap<PathBuf, Option<std::time::SystemTime>> = catalog_files().into_iter().map(|p| { let t = stamp(&p); (p, t) }).collect();
+    loop {
+      std::thread::sleep(CATALOG_POLL);
+      let changed: Vec<PathBuf> = catalog_files().into_iter().filter(|p| {
+        let t = stamp(p);
+        seen.insert(p.clone(), t) != Some(t)
+      }).collect();
+      if changed.is_empty() { continue; }
+      let r = reload_paths(changed);
+      for e in &r.errors { log::warn!("catalog reload: {e}"); }
+      crate::events::emit_global(&app, "catalog:reloaded", &r);
+    }
+  });
+}
+
+#[tauri::command]
+pub fn validate_catalog_file(path: String) -> Result<(), Vec<String>> {
+  load_catalog(Path::new(&path)).map(|_| ())
+}
+
+// sku -> unit_price from a previous result's items (grand total and regions), so a revision keeps
+// negotiated prices instead of falling back to the catalog
+#[tauri::command]
+pub fn import_price_overrides(prev_result_json: String) -> Result<HashMap<String, f64>, String> {
+  let v: serde_json::Value = serde_json::from_str(&prev_result_json).map_err(|e| format!("previous result: {e}"))?;
+  let mut out = HashMap::new();
+  let regions = v["regions"].as_object().into_iter().flat_map(|m| m.values()).map(|r| &r["items"]);
+  for items in std::iter::once(&v["items"]).chain(regions) {
+    for it in items.as_array().into_iter().flatten() {
+      if let (Some(sku), Some(price)) = (it["sku"].as_str(), it["unit_price"].as_f64()) { out.insert(sku.to_string(), price); }
+    }
+  }
+  Ok(out)
+}
+
+// label -> count, or with the weighted_counts setting label -> {count, expected} where expected is
+// the sum of detection scores. Quantities always use the raw count; expected is for review.
+fn symbol_counts(dets: &[crate::detect::Det], counts: &BTreeMap<&str, u32>) -> serde_json::Value {
+  if !crate::settings::get().weighted_counts { return serde_json::json!(counts); }
+  let mut expected: BTreeMap<&str, f64> = BTreeMap::new();
+  for d in dets { *expected.entry(d.label.as_str()).or_default() += d.score.clamp(0.0, 1.0) as f64; }
+  serde_json::Value::Object(counts.iter().map(|(l, n)| {
+    (l.to_string(), serde_json::json!({ "count": n, "expected": round_cents(expected.get(l).copied().unwrap_or(0.0)) }))
+  }).collect())
+}
+
+// `lineal` is the grand total; `lineal_by_label` the labeled part of it. A catalog row whose label
+// matches a lineal label is priced by the foot (qty_per per foot, rounded up).
+pub fn map_to_line_items(dets: &[crate::detect::Det], lineal: f64, lineal_by_label: &BTreeMap<String, f64>, area: f64, discipline: Option<char>, overrides: &HashMap<String, f64>) -> MappingResult {
+  // a missing catalog isn't fatal: counts still show up in the summary
+  let path = resolve_catalog_path(discipline);
+  let mut warnings = Vec::new();
+  let catalog = if path.exists() {
+    cached_catalog(&path).unwrap_or_else(|errs| { warnings.extend(errs); Vec::new() })
+  } else {
+    Vec::new()
+  };
+  map_with_catalog(dets, lineal, lineal_by_label, area, &catalog, overrides, warnings)
+}
+
+// map_to_line_items once the catalog is in hand; `warnings` carries any from loading it
+fn map_with_catalog(dets: &[crate::detect::Det], lineal: f64, lineal_by_label: &BTreeMap<String, f64>, area: f64, catalog: &[CatalogEntry], overrides: &HashMap<String, f64>, mut warnings: Vec<String>) -> MappingResult {
+  // a called-out member prices as its "label callout" row ("stud 2x4") when the catalog has one
+  let dets: Vec<crate::detect::Det> = dets.iter().map(|d| match &d.callout {
+    Some(c) if catalog.iter().any(|e| e.label == format!("{} {c}", d.label)) => crate::detect::Det { label: format!("{} {c}", d.label), ..d.clone() },
+    _ => d.clone(),
+  }).collect();
+  let dets = dets.as_slice();
+  let mut counts: BTreeMap<&str, u32> = BTreeMap::new();
+  for d in dets { *counts.entry(d.label.as_str()).or_default() += 1; }
+  let item = |e: &CatalogEntry, qty: f64| LineItem {
+    sku: e.sku.clone(),
+    qty: (qty * e.qty_per).ceil() as u32,
+    material: e.material.clone(),
+    finish: e.finish.clone(),
+    unit_price: overrides.get(&e.sku).copied().unwrap_or(e.unit_price),
+  };
+  let mut items = Vec::new();
+  let mut unmapped = Vec::new();
+  for (label, &n) in &counts {
+    let Some(e) = catalog.iter().find(|e| e.label == *label) else {
+      unmapped.push(UnmappedLabel { label: label.to_string(), count: n });
+      continue;
+    };
+    items.push(item(e, n as f64));
+  }
+  for (label, &feet) in lineal_by_label {
+    let Some(e) = catalog.iter().find(|e| e.label == *label) else { continue };
+    if counts.contains_key(label.as_str()) {
+      warnings.push(format!("`{label}` is both a symbol and a lineal label; its catalog row is priced twice"));
+    }
+    items.push(item(e, feet));
+  }
+  items.sort_by(|a, b| a.sku.cmp(&b.sku));
+
+  let mut summary = MappingSummary {
+    symbols: symbol_counts(dets, &counts),
+    lineal_feet: lineal,
+    lineal_by_label: lineal_by_label.clone(),
+    area_sqft: area,
+    circles: 0,
+    subtotal: 0.0, overhead: 0.0, profit: 0.0, tax: 0.0, total: 0.0,
+    currency: String::new(),
+  };
+  summary.apply_pricing(&items, &crate::settings::get().pricing);
+  MappingResult { summary, items, warnings, unmapped }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RegionReport {
+  pub regions: BTreeMap<String, MappingResult>,
+  pub total: MappingResult,
+}
+
+// subtotals per measurement region. `units_per_pixel` is for measurements that don't carry their page's scale. A detection belongs to the first tagged closed measurement
+// containing its center; everything else lands in "default".
+pub fn map_by_region(dets: &[crate::detect::Det], measurements: &[crate::measure::Measurement], units_per_pixel: f64, discipline: Option<char>, overrides: &HashMap<String, f64>) -> RegionReport {
+  use crate::measure::{area_with_holes, length_px, point_in_shape, DEFAULT_REGION};
+  let mut lineal: BTreeMap<String, f64> = BTreeMap::new();
+  // region -> label -> feet
+  let mut labeled: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+  let mut area: BTreeMap<String, f64> = BTreeMap::new();
+  for m in measurements {
+    let r = m.region_name().to_string();
+    let upp = m.scale_or(units_per_pixel);
+    if m.closed {
+      *area.entry(r).or_default() += area_with_holes(&m.points) * upp * upp;
+    } else {
+      let feet = length_px(&m.points) * upp;
+      if let Some(label) = &m.label { *labeled.entry(r.clone()).or_default().entry(label.clone()).or_default() += feet; }
+      *lineal.entry(r).or_default() += feet;
+    }
+  }
+  let mut labeled_total: BTreeMap<String, f64> = BTreeMap::new();
+  for (label, feet) in labeled.values().flatten() { *labeled_total.entry(label.clone()).or_default() += feet; }
+  let none = BTreeMap::new();
+  let mut by_region: BTreeMap<String, Vec<crate::detect::Det>> = BTreeMap::new();
+  for d in dets {
+    let c = (d.x + d.w * 0.5, d.y + d.h * 0.5);
+    let r = measurements.iter()
+      .find(|m| m.closed && m.region.is_some() && point_in_shape(c, &m.points))
+      .map(|m| m.region_name())
+      .unwrap_or(DEFAULT_REGION);
+    by_region.entry(r.to_string()).or_default().push(d.clone());
+  }
+  let names: std::collections::BTreeSet<String> = lineal.keys().chain(area.keys()).chain(by_region.keys()).cloned().collect();
+  let regions = names.into_iter().map(|r| {
+    let m = map_to_line_items(by_region.get(&r).map(Vec::as_slice).unwrap_or(&[]), lineal.get(&r).copied().unwrap_or(0.0), labeled.get(&r).unwrap_or(&none), area.get(&r).copied().unwrap_or(0.0), discipline, overrides);
+    (r, m)
+  }).collect();
+  let total = map_to_line_items(dets, lineal.values().sum(), &labeled_total, area.values().sum(), discipline, overrides);
+  RegionReport { regions, total }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn det(label: &str) -> crate::detect::Det { crate::detect::Det { x: 0.0, y: 0.0, w: 10.0, h: 10.0, label: label.into(), score: 0.9, callout: None } }
+
+  #[test]
+  fn labels_missing_from_the_catalog_are_unmapped_and_unpriced() {
+    let catalog = vec![CatalogEntry { label: "exit_sign".into(), sku: "EX-1".into(), material: "acrylic".into(), finish: "red".into(), qty_per: 1.0, unit_price: 40.0 }];
+    let dets = [det("exit_sign"), det("exit_sign"), det("mystery"), det("mystery"), det("mystery")];
+    let r = map_with_catalog(&dets, 0.0, &BTreeMap::new(), 0.0, &catalog, &HashMap::new(), vec![]);
+    assert_eq!(r.unmapped.len(), 1);
+    assert_eq!((r.unmapped[0].label.as_str(), r.unmapped[0].count), ("mystery", 3));
+    assert_eq!(r.items.len(), 1);
+    assert_eq!(r.items[0].sku, "EX-1");
+    assert_eq!(r.summary.subtotal, 80.0);
+    // still counted in the summary
+    assert_eq!(r.summary.symbols["mystery"], 3);
+  }
+
+  #[test]
+  fn callouts_pick_the_catalog_row_for_the_member() {
+    let row = |label: &str, sku: &str| CatalogEntry { label: label.into(), sku: sku.into(), material: "wood".into(), finish: String::new(), qty_per: 1.0, unit_price: 5.0 };
+    let catalog = vec![row("stud", "ST"), row("stud 2x4", "ST-24")];
+    let called = |c: &str| crate::detect::Det { callout: Some(c.into()), ..det("stud") };
+    // a 2x6 has no row of its own and falls back to the plain label
+    let dets = [called("2x4"), called("2x4"), called("2x6"), det("stud")];
+    let r = map_with_catalog(&dets, 0.0, &BTreeMap::new(), 0.0, &catalog, &HashMap::new(), vec![]);
+    let qty: BTreeMap<&str, u32> = r.items.iter().map(|i| (i.sku.as_str(), i.qty)).collect();
+    assert_eq!(qty, BTreeMap::from([("ST", 2), ("ST-24", 2)]));
+  }
+}
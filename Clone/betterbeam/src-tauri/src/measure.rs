@@ -1,14 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+// a traced measurement in page pixels; closed ones are areas, open ones lineal runs
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Measurement {
+  pub points: Vec<(f32, f32)>,
+  #[serde(default)]
+  pub closed: bool,
+  // grouping for subtotals (floor, room, wing); untagged counts toward "default"
+  #[serde(default)]
+  pub region: Option<String>,
+  // material the run stands for ("conduit", "baseboard"); lineal totals are broken down by it
+  #[serde(default)]
+  pub label: Option<String>,
+  // scale of the page it was traced on; unset means the document's
+  #[serde(default)]
+  pub units_per_pixel: Option<f64>,
+}
+
+impl Measurement {
+  pub fn region_name(&self) -> &str { self.region.as_deref().unwrap_or(DEFAULT_REGION) }
+
+  pub fn scale_or(&self, document: f64) -> f64 { self.units_per_pixel.unwrap_or(document) }
+}
+
+pub const DEFAULT_REGION: &str = "default";
+
+// runs split by LOOP_BREAK are measured separately, never across the break
 pub fn length_px(points: &[(f32, f32)]) -> f64 {
   let mut sum = 0.0_f64;
-  for w in points.windows(2) {
-    let (x1, y1) = (w[0].0 as f64, w[0].1 as f64);
-    let (x2, y2) = (w[1].0 as f64, w[1].1 as f64);
-    sum += ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+  for run in split_loops(points) {
+    for w in run.windows(2) {
+      let (x1, y1) = (w[0].0 as f64, w[0].1 as f64);
+      let (x2, y2) = (w[1].0 as f64, w[1].1 as f64);
+      sum += ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+    }
   }
   sum
 }
 
-pub fn area_px(points: &[(f32, f32)]) -> f64 {
+pub fn area_px(points: &[(f32, f32)]) -> f64 { signed_area(points).abs() }
+
+// shoelace area keeping the winding: positive for counter-clockwise in y-up coordinates, which is
+// clockwise on screen (pixels, y down)
+pub fn signed_area(points: &[(f32, f32)]) -> f64 {
   if points.len() < 3 { return 0.0; }
   let mut area = 0.0_f64;
   for i in 0..points.len() {
@@ -16,7 +50,398 @@ pub fn area_px(points: &[(f32, f32)]) -> f64 {
     let (x2, y2) = (points[(i + 1) % points.len()].0 as f64, points[(i + 1) % points.len()].1 as f64);
     area += x1 * y2 - x2 * y1;
   }
-  area.abs() * 0.5
+  area * 0.5
+}
+
+// goes between loops in one point list: outline, LOOP_BREAK, opening, LOOP_BREAK, ... Finite so it
+// survives JSON; no real drawing coordinate gets near it.
+pub const LOOP_BREAK: (f32, f32) = (f32::MIN, f32::MIN);
+
+pub fn split_loops(points: &[(f32, f32)]) -> Vec<&[(f32, f32)]> {
+  points.split(|p| *p == LOOP_BREAK).filter(|l| !l.is_empty()).collect()
+}
+
+// one point list holding an outline and its openings, the way CAD authors them: loops wound like the
+// largest one are solid, loops wound the other way are holes. Without LOOP_BREAK it's area_px.
+pub fn area_with_holes(points: &[(f32, f32)]) -> f64 {
+  let areas: Vec<f64> = split_loops(points).into_iter().map(signed_area).collect();
+  let Some(outer) = areas.iter().copied().max_by(|a, b| a.abs().total_cmp(&b.abs())) else { return 0.0 };
+  let sum: f64 = areas.iter().map(|a| if a.signum() == outer.signum() { a.abs() } else { -a.abs() }).sum();
+  sum.max(0.0)
+}
+
+// a single loop; lists with LOOP_BREAK go through point_in_shape
+pub fn point_in_polygon(p: (f32, f32), poly: &[(f32, f32)]) -> bool {
+  let mut inside = false;
+  let n = poly.len();
+  for i in 0..n {
+    let (a, b) = (poly[i], poly[(i + n - 1) % n]);
+    if (a.1 > p.1) != (b.1 > p.1) && p.0 < (b.0 - a.0) * (p.1 - a.1) / (b.1 - a.1) + a.0 { inside = !inside; }
+  }
+  inside
+}
+
+// inside a solid loop and not inside an opening, with the winding rules of area_with_holes
+pub fn point_in_shape(p: (f32, f32), points: &[(f32, f32)]) -> bool {
+  let loops = split_loops(points);
+  let Some(outer) = loops.iter().map(|l| signed_area(l)).max_by(|a, b| a.abs().total_cmp(&b.abs())) else { return false };
+  let (mut solid, mut hole) = (false, false);
+  for l in loops {
+    if !point_in_polygon(p, l) { continue; }
+    if signed_area(l).signum() == outer.signum() { solid = true } else { hole = true }
+  }
+  solid && !hole
+}
+
+// strip of `width` around a centerline: left side forward, right side back, flat caps. Joins are
+// mitered, falling back to a bevel past a 4x miter so hairpins don't shoot spikes or divide by zero.
+pub fn buffer_polyline(points: &[(f32, f32)], width: f64) -> Vec<(f32, f32)> {
+  let mut pts: Vec<(f64, f64)> = points.iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+  pts.dedup_by(|a, b| (a.0 - b.0).hypot(a.1 - b.1) < 1e-9);
+  if pts.len() < 2 || width.is_nan() || width <= 0.0 { return vec![]; }
+  let half = width * 0.5;
+  let normal = |a: (f64, f64), b: (f64, f64)| {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let l = dx.hypot(dy);
+    (-dy / l, dx / l)
+  };
+  const MITER_LIMIT: f64 = 4.0;
+  let side = |sign: f64| {
+    let mut out = Vec::new();
+    let n = pts.len();
+    for i in 0..n {
+      let np = if i > 0 { Some(normal(pts[i - 1], pts[i])) } else { None };
+      let nn = if i + 1 < n { Some(normal(pts[i], pts[i + 1])) } else { None };
+      let p = pts[i];
+      match (np, nn) {
+        (Some(a), Some(b)) => {
+          let (mx, my) = (a.0 + b.0, a.1 + b.1);
+          let ml = mx.hypot(my);
+          let cos = if ml > 1e-9 { (mx / ml) * a.0 + (my / ml) * a.1 } else { 0.0 };
+          if cos > 1.0 / MITER_LIMIT {
+            let d = half / cos;
+            out.push((p.0 + sign * mx / ml * d, p.1 + sign * my / ml * d));
+          } else {
+            out.push((p.0 + sign * a.0 * half, p.1 + sign * a.1 * half));
+            out.push((p.0 + sign * b.0 * half, p.1 + sign * b.1 * half));
+          }
+        }
+        (Some(a), None) | (None, Some(a)) => out.push((p.0 + sign * a.0 * half, p.1 + sign * a.1 * half)),
+        (None, None) => {}
+      }
+    }
+    out
+  };
+  let mut poly = side(1.0);
+  poly.extend(side(-1.0).into_iter().rev());
+  poly.into_iter().map(|(x, y)| (x as f32, y as f32)).collect()
+}
+
+// face area of a wall traced along its centerline, in pixels²: the buffered strip's area
+pub fn wall_area_px(points: &[(f32, f32)], width_px: f64) -> f64 { area_px(&buffer_polyline(points, width_px)) }
+
+// nearest grid intersection; spacing <= 0 leaves the point as is
+pub fn snap_to_grid(p: (f32, f32), spacing: f32, origin: (f32, f32)) -> (f32, f32) {
+  if spacing.is_nan() || spacing <= 0.0 { return p; }
+  let snap = |v: f32, o: f32| o + ((v - o) / spacing).round() * spacing;
+  (snap(p.0, origin.0), snap(p.1, origin.1))
+}
+
+// locks prev→cur to the nearest multiple of 45° when within `snap_deg` of it (projection onto that ray)
+pub fn ortho_constrain(prev: (f32, f32), cur: (f32, f32), snap_deg: f32) -> (f32, f32) {
+  let (dx, dy) = (cur.0 - prev.0, cur.1 - prev.1);
+  if dx == 0.0 && dy == 0.0 { return cur; }
+  let ang = dy.atan2(dx).to_degrees();
+  let axis = (ang / 45.0).round() * 45.0;
+  if (ang - axis).abs() > snap_deg { return cur; }
+  let (ux, uy) = (axis.to_radians().cos(), axis.to_radians().sin());
+  let t = dx * ux + dy * uy;
+  // exact zeros on the axes so horizontal/vertical runs come out perfectly straight
+  let clean = |v: f32| if v.abs() < 1e-6 { 0.0 } else { v };
+  (prev.0 + t * clean(ux), prev.1 + t * clean(uy))
+}
+
+// grid spacing is given in real units (e.g. 1 ft) and converted with the page scale
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SnapOptions {
+  pub grid_spacing: Option<f64>,
+  pub units_per_pixel: f64,
+  pub grid_origin: (f32, f32),
+  // ortho mode: snap each segment to 0/45/90° when within this many degrees
+  pub ortho_deg: Option<f32>,
+}
+
+impl SnapOptions {
+  pub fn spacing_px(&self) -> Option<f32> {
+    let s = self.grid_spacing?;
+    (s > 0.0 && self.units_per_pixel > 0.0).then(|| (s / self.units_per_pixel) as f32)
+  }
+
+  // ortho first, relative to the already-snapped previous point, then the grid
+  pub fn apply(&self, points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut out: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+    for &p in points {
+      let mut q = match (self.ortho_deg, out.last()) { (Some(deg), Some(&prev)) => ortho_constrain(prev, p, deg), _ => p };
+      if let Some(sp) = self.spacing_px() { q = snap_to_grid(q, sp, self.grid_origin); }
+      out.push(q);
+    }
+    out
+  }
 }
 
+// errors are "code: detail" so the UI can branch on the code
+fn check_points(points: &[(f32, f32)], min: usize) -> Result<(), String> {
+  if points.len() < min { return Err(format!("too_few_points: need at least {min} points, got {}", points.len())); }
+  if let Some(i) = points.iter().position(|p| !p.0.is_finite() || !p.1.is_finite()) { return Err(format!("invalid_point: point {i} is not finite")); }
+  Ok(())
+}
+
+fn check_factor(name: &str, v: f64) -> Result<(), String> {
+  if !v.is_finite() || v <= 0.0 { return Err(format!("invalid_scale: {name} must be positive, got {v}")); }
+  Ok(())
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasurementKind { Linear, Polygon, Count, Angle }
+
+// what compute_measurement returns, tagged with the kind; real units come from the scale, pixels are kept for redraws
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MeasurementValue {
+  Linear { length: f64, length_px: f64 },
+  Polygon { area: f64, perimeter: f64, area_px: f64 },
+  Count { count: usize },
+  // at the middle point, 0..=180
+  Angle { degrees: f64 },
+}
+
+// interior angle at b of a→b→c; None when either arm has zero length
+pub fn angle_deg(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> Option<f64> {
+  let (ux, uy) = ((a.0 - b.0) as f64, (a.1 - b.1) as f64);
+  let (vx, vy) = ((c.0 - b.0) as f64, (c.1 - b.1) as f64);
+  if ux.hypot(uy) < 1e-9 || vx.hypot(vy) < 1e-9 { return None; }
+  Some((ux * vy - uy * vx).abs().atan2(ux * vx + uy * vy).to_degrees())
+}
+
+// the one place a kind's point rules and units live; `units_per_pixel` is ignored by count and angle
+pub fn compute(kind: MeasurementKind, points: &[(f32, f32)], units_per_pixel: f64) -> Result<MeasurementValue, String> {
+  match kind {
+    MeasurementKind::Linear => {
+      check_points(points, 2)?;
+      check_factor("units_per_pixel", units_per_pixel)?;
+      let px = length_px(points);
+      Ok(MeasurementValue::Linear { length: px * units_per_pixel, length_px: px })
+    }
+    // openings may follow the outline after LOOP_BREAK, see area_with_holes
+    MeasurementKind::Polygon => {
+      let loops = split_loops(points);
+      if loops.is_empty() { check_points(points, 3)?; }
+      for l in &loops { check_points(l, 3)?; }
+      check_factor("units_per_pixel", units_per_pixel)?;
+      let px = area_with_holes(points);
+      let perimeter: f64 = loops.iter().map(|l| {
+        let mut ring = l.to_vec();
+        ring.push(l[0]);
+        length_px(&ring)
+      }).sum();
+      Ok(MeasurementValue::Polygon { area: px * units_per_pixel * units_per_pixel, perimeter: perimeter * units_per_pixel, area_px: px })
+    }
+    MeasurementKind::Count => {
+      check_points(points, 1)?;
+      Ok(MeasurementValue::Count { count: points.len() })
+    }
+    MeasurementKind::Angle => {
+      check_points(points, 3)?;
+      if points.len() != 3 { return Err(format!("too_many_points: an angle takes exactly 3 points, got {}", points.len())); }
+      let degrees = angle_deg(points[0], points[1], points[2]).ok_or("degenerate_angle: an arm has zero length")?;
+      Ok(MeasurementValue::Angle { degrees })
+    }
+  }
+}
 
+// single entry point for the measuring tools
+#[tauri::command]
+pub fn compute_measurement(kind: MeasurementKind, points: Vec<(f32, f32)>, scale: f64) -> Result<MeasurementValue, String> {
+  compute(kind, &points, scale)
+}
+
+// polyline length in real units
+#[tauri::command]
+pub fn measure_length(points: Vec<(f32, f32)>, units_per_pixel: f64) -> Result<f64, String> {
+  check_points(&points, 2)?;
+  check_factor("units_per_pixel", units_per_pixel)?;
+  Ok(length_px(&points) * units_per_pixel)
+}
+
+// polygon area in real units; takes the squared factor so callers don't square twice by mistake; openings after LOOP_BREAK are subtracted
+#[tauri::command]
+pub fn measure_area(points: Vec<(f32, f32)>, units_per_pixel_sq: f64) -> Result<f64, String> {
+  check_points(&points, 3)?;
+  check_factor("units_per_pixel_sq", units_per_pixel_sq)?;
+  Ok(area_with_holes(&points) * units_per_pixel_sq)
+}
+
+// centerline trace and a width in real units; the face area comes back in real units²
+#[tauri::command]
+pub fn wall_area(points: Vec<(f32, f32)>, width: f64, units_per_pixel: f64) -> Result<f64, String> {
+  check_points(&points, 2)?;
+  check_factor("units_per_pixel", units_per_pixel)?;
+  check_factor("width", width)?;
+  Ok(wall_area_px(&points, width / units_per_pixel) * units_per_pixel * units_per_pixel)
+}
+
+#[tauri::command]
+pub fn snap_points(points: Vec<(f32, f32)>, snap: SnapOptions) -> Vec<(f32, f32)> {
+  snap.apply(&points)
+}
+
+// area covered by any polygon, overlaps counted once. Slabs between every vertex x and every pairwise
+// edge-crossing x have a cross-section that varies linearly, so midpoint length × width is exact.
+// A polygon's LOOP_BREAK loops all feed its even-odd crossings, so openings stay open.
+pub fn union_area(polys: &[Vec<(f32, f32)>]) -> f64 {
+  let edges: Vec<((f64, f64), (f64, f64), usize)> = polys.iter().enumerate()
+    .flat_map(|(pi, p)| split_loops(p).into_iter().filter(|l| l.len() >= 3).flat_map(move |l| (0..l.len()).map(move |i| {
+      let (a, b) = (l[i], l[(i + 1) % l.len()]);
+      ((a.0 as f64, a.1 as f64), (b.0 as f64, b.1 as f64), pi)
+    })))
+    .collect();
+  let mut xs: Vec<f64> = edges.iter().map(|e| e.0 .0).collect();
+  for i in 0..edges.len() {
+    for j in i + 1..edges.len() {
+      let ((p, r), (q, s)) = ((edges[i].0, edges[i].1), (edges[j].0, edges[j].1));
+      let (rx, ry, sx, sy) = (r.0 - p.0, r.1 - p.1, s.0 - q.0, s.1 - q.1);
+      let den = rx * sy - ry * sx;
+      if den.abs() < 1e-12 { continue; }
+      let t = ((q.0 - p.0) * sy - (q.1 - p.1) * sx) / den;
+      let u = ((q.0 - p.0) * ry - (q.1 - p.1) * rx) / den;
+      if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) { xs.push(p.0 + t * rx); }
+    }
+  }
+  xs.sort_by(f64::total_cmp);
+  xs.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+  let mut total = 0.0;
+  for w in xs.windows(2) {
+    let x = (w[0] + w[1]) * 0.5;
+    // even-odd crossings per polygon give its intervals on this vertical line
+    let mut intervals: Vec<(f64, f64)> = Vec::new();
+    for pi in 0..polys.len() {
+      let mut ys: Vec<f64> = edges.iter()
+        .filter(|e| e.2 == pi && (e.0 .0 <= x) != (e.1 .0 <= x))
+        .map(|e| e.0 .1 + (x - e.0 .0) / (e.1 .0 - e.0 .0) * (e.1 .1 - e.0 .1))
+        .collect();
+      ys.sort_by(f64::total_cmp);
+      intervals.extend(ys.chunks_exact(2).map(|c| (c[0], c[1])));
+    }
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (mut len, mut cur): (f64, Option<(f64, f64)>) = (0.0, None);
+    for (a, b) in intervals {
+      cur = match cur {
+        Some((s, e)) if a <= e => Some((s, e.max(b))),
+        Some((s, e)) => { len += e - s; Some((a, b)) }
+        None => Some((a, b)),
+      };
+    }
+    if let Some((s, e)) = cur { len += e - s; }
+    total += len * (w[1] - w[0]);
+  }
+  total
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SQUARE: [(f32, f32); 4] = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+
+  fn reversed(points: &[(f32, f32)]) -> Vec<(f32, f32)> { points.iter().rev().copied().collect() }
+
+  fn with_loops(loops: &[&[(f32, f32)]]) -> Vec<(f32, f32)> {
+    loops.iter().enumerate().flat_map(|(i, &l)| (i > 0).then_some(LOOP_BREAK).into_iter().chain(l.iter().copied())).collect()
+  }
+
+  fn inner() -> Vec<(f32, f32)> { vec![(3.0, 3.0), (7.0, 3.0), (7.0, 7.0), (3.0, 7.0)] }
+
+  #[test]
+  fn winding_flips_the_sign() {
+    let a = signed_area(&SQUARE);
+    let b = signed_area(&reversed(&SQUARE));
+    assert_eq!(a, 100.0);
+    assert_eq!(b, -100.0);
+  }
+
+  #[test]
+  fn opposite_winding_loop_is_a_hole() {
+    let pts = with_loops(&[&SQUARE, &reversed(&inner())]);
+    assert_eq!(area_with_holes(&pts), 100.0 - 16.0);
+    assert!(point_in_shape((1.0, 1.0), &pts));
+    assert!(!point_in_shape((5.0, 5.0), &pts));
+    assert!(!point_in_shape((-5.0, -5.0), &pts));
+  }
+
+  #[test]
+  fn same_winding_loop_is_solid() {
+    let far: Vec<(f32, f32)> = SQUARE.iter().map(|p| (p.0 + 20.0, p.1)).collect();
+    let pts = with_loops(&[&SQUARE, &far]);
+    assert_eq!(area_with_holes(&pts), 200.0);
+    assert!(point_in_shape((25.0, 5.0), &pts));
+  }
+
+  #[test]
+  fn loop_break_is_not_measured() {
+    let pts = with_loops(&[&[(0.0, 0.0), (10.0, 0.0)], &[(0.0, 5.0), (0.0, 8.0)]]);
+    assert_eq!(length_px(&pts), 13.0);
+    let holed = with_loops(&[&SQUARE, &reversed(&inner())]);
+    assert!((union_area(&[holed]) - 84.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn snap_to_grid_at_the_origin() {
+    assert_eq!(snap_to_grid((12.0, -7.0), 10.0, (0.0, 0.0)), (10.0, -10.0));
+    assert_eq!(snap_to_grid((4.9, 5.1), 10.0, (0.0, 0.0)), (0.0, 10.0));
+  }
+
+  #[test]
+  fn snap_to_grid_off_centre_origin() {
+    assert_eq!(snap_to_grid((12.0, 16.0), 10.0, (3.0, 4.0)), (13.0, 14.0));
+    assert_eq!(snap_to_grid((3.0, 4.0), 10.0, (3.0, 4.0)), (3.0, 4.0));
+  }
+
+  #[test]
+  fn ortho_snaps_near_horizontal() {
+    assert_eq!(ortho_constrain((0.0, 0.0), (10.0, 0.5), 5.0), (10.0, 0.0));
+  }
+
+  #[test]
+  fn ortho_snaps_near_45() {
+    let (x, y) = ortho_constrain((0.0, 0.0), (10.0, 9.5), 5.0);
+    assert!((x - y).abs() < 1e-4);
+    assert!((x.hypot(y) - 19.5 / 2f32.sqrt()).abs() < 1e-3);
+    // past the tolerance the point is left alone
+    assert_eq!(ortho_constrain((0.0, 0.0), (10.0, 4.0), 5.0), (10.0, 4.0));
+  }
+
+  #[test]
+  fn union_counts_the_overlap_once() {
+    let a = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+    let b = vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)];
+    let overlap = 1.0;
+    assert!((union_area(&[a.clone(), b.clone()]) - (area_px(&a) + area_px(&b) - overlap)).abs() < 1e-9);
+  }
+
+  #[test]
+  fn straight_wall_is_length_times_width() {
+    assert!((wall_area_px(&[(0.0, 0.0), (100.0, 0.0)], 10.0) - 1000.0).abs() < 1e-3);
+    assert!((wall_area(vec![(0.0, 0.0), (100.0, 0.0)], 0.5, 0.1).unwrap() - 10.0 * 0.5).abs() < 1e-6);
+  }
+
+  #[test]
+  fn hairpin_wall_has_no_nans() {
+    let trace = [(0.0, 0.0), (100.0, 0.0), (0.0, 1.0)];
+    assert!(buffer_polyline(&trace, 10.0).iter().all(|p| p.0.is_finite() && p.1.is_finite()));
+    let a = wall_area_px(&trace, 10.0);
+    assert!(a.is_finite() && a > 0.0);
+  }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+
+// a run of recognized text; box is in image pixels (top-left origin)
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TextRun { pub text: String, pub x: f32, pub y: f32, pub w: f32, pub h: f32, pub conf: f32 }
+
+impl TextRun {
+  pub fn center(&self) -> (f32, f32) { (self.x + self.w * 0.5, self.y + self.h * 0.5) }
+}
+
+// one page through the engine; boxes in `img` pixels
+fn recognize(img: &image::DynamicImage, lang: &str) -> Result<Vec<TextRun>, String> {
+  // TODO: native tesseract binding; until then the frontend's tesseract.js is the only engine
+  let _ = (img, lang);
+  Ok(vec![])
+}
+
+// installed languages of the native engine; None until recognize has a real engine behind it
+pub fn engine_info() -> Option<Vec<String>> { None }
+
+// a page's text plus how the scan was turned. `orientation` is the clockwise rotation that made it
+// upright; runs are mapped back into the unrotated page image, so they line up with its render.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct OcrPage { pub orientation: u16, pub runs: Vec<TextRun> }
+
+// upright pages usually read at this mean confidence; below it the other turns are tried
+const UPRIGHT_CONF: f32 = 80.0;
+
+fn score(runs: &[TextRun]) -> f32 { runs.iter().map(|r| r.conf.max(0.0)).sum() }
+
+// box found in the image turned `deg` clockwise, back in the original `w`×`h` image
+fn unrotate(r: TextRun, deg: u16, w: f32, h: f32) -> TextRun {
+  let (x, y, bw, bh) = match deg {
+    90 => (r.y, h - r.x - r.w, r.h, r.w),
+    180 => (w - r.x - r.w, h - r.y - r.h, r.w, r.h),
+    270 => (w - r.y - r.h, r.x, r.h, r.w),
+    _ => (r.x, r.y, r.w, r.h),
+  };
+  TextRun { x, y, w: bw, h: bh, ..r }
+}
+
+// tries 0° first and only pays for the other three turns when that reads poorly
+pub fn ocr_page(img: &image::DynamicImage, lang: &str) -> Result<OcrPage, String> {
+  best_orientation(img, |i| recognize(i, lang))
+}
+
+// the turn selection behind ocr_page, over any reader
+fn best_orientation(img: &image::DynamicImage, read: impl Fn(&image::DynamicImage) -> Result<Vec<TextRun>, String>) -> Result<OcrPage, String> {
+  let upright = read(img)?;
+  let mean = if upright.is_empty() { 0.0 } else { score(&upright) / upright.len() as f32 };
+  let mut best = OcrPage { orientation: 0, runs: upright };
+  if mean >= UPRIGHT_CONF { return Ok(best); }
+  for deg in [90u16, 180, 270] {
+    let turned = match deg { 90 => img.rotate90(), 180 => img.rotate180(), _ => img.rotate270() };
+    let runs = read(&turned)?;
+    if score(&runs) > score(&best.runs) { best = OcrPage { orientation: deg, runs }; }
+  }
+  let (w, h) = (img.width() as f32, img.height() as f32);
+  let deg = best.orientation;
+  best.runs = best.runs.into_iter().map(|r| unrotate(r, deg, w, h)).collect();
+  Ok(best)
+}
+
+// OCR results for one document at one dpi/language, by page
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+struct OcrCache {
+  dpi: u32,
+  lang: String,
+  pages: std::collections::BTreeMap<u32, OcrPage>,
+}
+
+// in memory by pdf path, and on disk next to the PDF so a reopened project doesn't redo the work
+static CACHE: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashMap<String, OcrCache>>> =
+  once_cell::sync::Lazy::new(Default::default);
+
+fn sidecar(pdf_path: &str) -> std::path::PathBuf { std::path::PathBuf::from(format!("{pdf_path}.ocr.json")) }
+
+fn cached(pdf_path: &str, dpi: u32, lang: &str) -> OcrCache {
+  let fresh = || OcrCache { dpi, lang: lang.to_string(), pages: Default::default() };
+  let mut mem = CACHE.lock();
+  let c = mem.entry(pdf_path.to_string()).or_insert_with(|| {
+    std::fs::read_to_string(sidecar(pdf_path)).ok().and_then(|t| serde_json::from_str(&t).ok()).unwrap_or_else(fresh)
+  });
+  // a different dpi or language means different boxes
+  if c.dpi != dpi || c.lang != lang { *c = fresh(); }
+  c.clone()
+}
+
+fn store(pdf_path: &str, cache: OcrCache) {
+  if let Ok(text) = serde_json::to_string(&cache) {
+    // best effort: a read-only project folder just means no persistence
+    if let Err(e) = std::fs::write(sidecar(pdf_path), text) { log::warn!("saving OCR cache for {pdf_path}: {e}"); }
+  }
+  CACHE.lock().insert(pdf_path.to_string(), cache);
+}
+
+// every page, cached ones skipped, the rest in parallel up to the page concurrency setting
+pub fn ocr_all(pdf_path: &str, dpi: u32, lang: &str) -> Result<Vec<OcrPage>, String> {
+  use rayon::prelude::*;
+  crate::pdf::check_dpi(dpi)?;
+  let mut cache = cached(pdf_path, dpi, lang);
+  let n = crate::pdf::page_count(pdf_path)?;
+  let todo: Vec<u32> = (0..n).filter(|p| !cache.pages.contains_key(p)).collect();
+  if !todo.is_empty() {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(crate::settings::get().page_concurrency).build().map_err(|e| e.to_string())?;
+    let done: Vec<(u32, OcrPage)> = pool.install(|| {
+      todo.into_par_iter().map(|page| {
+        let lib = crate::pdf::bind()?;
+        let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| e.to_string())?;
+        Ok((page, ocr_page(&crate::pdf::render_page(&doc, page, dpi)?, lang)?))
+      }).collect::<Result<_, String>>()
+    })?;
+    cache.pages.extend(done);
+    store(pdf_path, cache.clone());
+  }
+  Ok((0..n).map(|p| cache.pages.get(&p).cloned().unwrap_or_default()).collect())
+}
+
+#[tauri::command]
+pub async fn ocr_document(pdf_path: String, dpi: Option<u32>, lang: Option<String>) -> Result<Vec<OcrPage>, String> {
+  let (dpi, lang) = (dpi.unwrap_or(300), lang.unwrap_or_else(|| "eng".into()));
+  tauri::async_runtime::spawn_blocking(move || ocr_all(&pdf_path, dpi, &lang)).await.map_err(|e| e.to_string())?
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct TextHit { pub page: u32, pub run: TextRun }
+
+// case-insensitive substring search over whatever has been OCR'd for the document
+#[tauri::command]
+pub fn search_text(pdf_path: String, query: String) -> Vec<TextHit> {
+  let q = query.trim().to_lowercase();
+  if q.is_empty() { return vec![]; }
+  // fall back to the sidecar so a reopened project is searchable before anything is re-run
+  let cache = CACHE.lock().get(&pdf_path).cloned()
+    .or_else(|| std::fs::read_to_string(sidecar(&pdf_path)).ok().and_then(|t| serde_json::from_str::<OcrCache>(&t).ok()));
+  let Some(cache) = cache else { return vec![] };
+  cache.pages.into_iter()
+    .flat_map(|(page, p)| p.runs.into_iter().map(move |run| TextHit { page, run }))
+    .filter(|h| h.run.text.to_lowercase().contains(&q))
+    .collect()
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct IndexedRun {
+  #[serde(flatten)]
+  pub run: TextRun,
+  // lowercased, whitespace collapsed: match the search box against this
+  pub norm: String,
+}
+
+fn normalize(s: &str) -> String { s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase() }
+
+// the PDF's own text layer, one run per pdfium segment (a stretch of text sharing a baseline and style)
+fn text_layer(pdf_path: &str, page: u32, dpi: u32) -> Result<Vec<TextRun>, String> {
+  let lib = crate::pdf::bind()?;
+  let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| e.to_string())?;
+  text_layer_of(&doc, page, dpi)
+}
+
+// same, on a document the caller already has open
+pub fn text_layer_of(doc: &pdfium_render::prelude::PdfDocument, page: u32, dpi: u32) -> Result<Vec<TextRun>, String> {
+  let t = crate::transform::Transform::for_page(doc, page, dpi)?;
+  let p = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+  let text = p.text().map_err(|e| e.to_string())?;
+  let mut out = Vec::new();
+  for seg in text.segments().iter() {
+    let s = seg.text();
+    if s.trim().is_empty() { continue; }
+    let b = seg.bounds();
+    let (a, c) = (t.point_to_pixel((b.left().value, b.top().value)), t.point_to_pixel((b.right().value, b.bottom().value)));
+    let (x, y) = (a.0.min(c.0), a.1.min(c.1));
+    out.push(TextRun { text: s, x, y, w: (a.0 - c.0).abs(), h: (a.1 - c.1).abs(), conf: 100.0 });
+  }
+  Ok(out)
+}
+
+// the text layer when the PDF has one, else whatever OCR has cached for the page; pixels at `dpi`
+pub fn page_runs(pdf_path: &str, page: u32, dpi: u32) -> Result<Vec<TextRun>, String> {
+  let runs = text_layer(pdf_path, page, dpi)?;
+  if !runs.is_empty() { return Ok(runs); }
+  // OCR boxes are in pixels at the dpi they were read at; rescale to the one asked for
+  let Some(c) = CACHE.lock().get(pdf_path).cloned() else { return Ok(runs) };
+  let k = dpi as f32 / c.dpi.max(1) as f32;
+  Ok(c.pages.get(&page).map(|p| p.runs.iter().map(|r| TextRun { x: r.x * k, y: r.y * k, w: r.w * k, h: r.h * k, ..r.clone() }).collect()).unwrap_or_default())
+}
+
+static TEXT_INDEX: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashMap<(String, u32, u32), Vec<IndexedRun>>>> =
+  once_cell::sync::Lazy::new(Default::default);
+
+// page_runs, normalized for search. Cached, so search-as-you-type is cheap.
+#[tauri::command]
+pub async fn page_text_index(pdf_path: String, page: u32, dpi: Option<u32>) -> Result<Vec<IndexedRun>, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  let key = (pdf_path.clone(), page, dpi);
+  if let Some(hit) = TEXT_INDEX.lock().get(&key) { return Ok(hit.clone()); }
+  let runs = tauri::async_runtime::spawn_blocking(move || page_runs(&pdf_path, page, dpi)).await.map_err(|e| e.to_string())??;
+  let indexed: Vec<IndexedRun> = runs.into_iter().map(|run| IndexedRun { norm: normalize(&run.text), run }).collect();
+  // an empty page may just not be OCR'd yet; don't pin that
+  if !indexed.is_empty() { TEXT_INDEX.lock().insert(key, indexed.clone()); }
+  Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // reads one confident word only when the ink marker sits top-left, i.e. the image is upright
+  fn marker_reader(img: &image::DynamicImage) -> Result<Vec<TextRun>, String> {
+    if img.to_luma8().get_pixel(5, 5)[0] > 128 { return Ok(vec![]); }
+    Ok(vec![TextRun { text: "DOOR SCHEDULE".into(), x: 10.0, y: 10.0, w: 50.0, h: 20.0, conf: 95.0 }])
+  }
+
+  #[test]
+  fn upside_down_page_reads_after_a_half_turn() {
+    let mut gray = image::GrayImage::from_pixel(200, 100, image::Luma([255]));
+    gray.put_pixel(194, 94, image::Luma([0]));
+    let page = best_orientation(&image::DynamicImage::ImageLuma8(gray), marker_reader).unwrap();
+    assert_eq!(page.orientation, 180);
+    assert_eq!(page.runs.len(), 1);
+    let r = &page.runs[0];
+    assert_eq!(r.text, "DOOR SCHEDULE");
+    // back in the unrotated image: mirrored through the centre
+    assert_eq!((r.x, r.y, r.w, r.h), (140.0, 70.0, 50.0, 20.0));
+  }
+
+  #[test]
+  fn unrotate_quarter_turns() {
+    let run = TextRun { text: "A".into(), x: 10.0, y: 20.0, w: 30.0, h: 5.0, conf: 90.0 };
+    // the 200x100 image turned 90° clockwise is 100x200
+    let r = unrotate(run.clone(), 90, 200.0, 100.0);
+    assert_eq!((r.x, r.y, r.w, r.h), (20.0, 60.0, 5.0, 30.0));
+    let r = unrotate(run, 0, 200.0, 100.0);
+    assert_eq!((r.x, r.y, r.w, r.h), (10.0, 20.0, 30.0, 5.0));
+  }
+}
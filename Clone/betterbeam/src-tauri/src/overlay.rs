@@ -0,0 +1,155 @@
+use crate::detect::Det;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+// what the viewer draws over a page; coordinates are pixels of the page render
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Overlay {
+  pub dets: Vec<Det>,
+  pub paths: Vec<OverlayPath>,
+  // free text such as the scale annotation, printed in the margin
+  pub notes: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct OverlayPath {
+  pub points: Vec<(f32, f32)>,
+  #[serde(default)]
+  pub closed: bool,
+  #[serde(default)]
+  pub label: Option<String>,
+  #[serde(default)]
+  pub color: Option<[u8; 3]>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LabelStyle { pub color: [u8; 3] }
+
+const PALETTE: [[u8; 3]; 10] = [
+  [220, 40, 40], [30, 140, 60], [240, 130, 20], [140, 60, 200], [0, 150, 170],
+  [200, 40, 140], [120, 100, 30], [60, 60, 220], [170, 170, 0], [90, 90, 90],
+];
+
+// FNV-1a, so a label gets the same palette slot on every machine and run
+fn palette_color(label: &str) -> [u8; 3] {
+  let h = label.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+  PALETTE[(h % PALETTE.len() as u64) as usize]
+}
+
+// saved colours for the given labels; unseen labels are assigned and persisted in one write
+pub fn label_colors<'a>(labels: impl IntoIterator<Item = &'a str>) -> std::collections::HashMap<String, [u8; 3]> {
+  let styles = crate::settings::get().label_styles;
+  let mut out = std::collections::HashMap::new();
+  let mut fresh = Vec::new();
+  for l in labels {
+    if out.contains_key(l) { continue; }
+    let c = match styles.get(l) { Some(s) => s.color, None => { fresh.push(l.to_string()); palette_color(l) } };
+    out.insert(l.to_string(), c);
+  }
+  if !fresh.is_empty() {
+    let res = crate::settings::update(|s| for l in &fresh { s.label_styles.insert(l.clone(), LabelStyle { color: out[l] }); });
+    if let Err(e) = res { log::warn!("saving label colours: {e}"); }
+  }
+  out
+}
+
+#[tauri::command]
+pub fn get_label_styles() -> std::collections::BTreeMap<String, LabelStyle> { crate::settings::get().label_styles }
+
+#[tauri::command]
+pub fn set_label_color(label: String, color: [u8; 3]) -> Result<(), String> {
+  crate::settings::update(|s| { s.label_styles.insert(label, LabelStyle { color }); }).map(|_| ())
+}
+
+const PATH_COLOR: [u8; 3] = [30, 110, 230];
+
+fn plot(img: &mut RgbaImage, x: i32, y: i32, c: [u8; 3]) {
+  if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 { return; }
+  img.put_pixel(x as u32, y as u32, Rgba([c[0], c[1], c[2], 255]));
+}
+
+pub fn draw_line(img: &mut RgbaImage, a: (f32, f32), b: (f32, f32), c: [u8; 3], thickness: u32) {
+  let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as i32;
+  let r = (thickness as i32 - 1) / 2;
+  for i in 0..=steps {
+    let t = i as f32 / steps as f32;
+    let (x, y) = ((a.0 + (b.0 - a.0) * t).round() as i32, (a.1 + (b.1 - a.1) * t).round() as i32);
+    for dy in -r..=r { for dx in -r..=r { plot(img, x + dx, y + dy, c); } }
+  }
+}
+
+pub fn draw_rect(img: &mut RgbaImage, x: f32, y: f32, w: f32, h: f32, c: [u8; 3], thickness: u32) {
+  let pts = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+  for i in 0..4 { draw_line(img, pts[i], pts[(i + 1) % 4], c, thickness); }
+}
+
+fn stroke(img: &RgbaImage) -> u32 { (img.width().max(img.height()) / 1500).max(2) }
+
+pub fn draw_detections(img: &mut RgbaImage, dets: &[Det]) {
+  let t = stroke(img);
+  let colors = label_colors(dets.iter().map(|d| d.label.as_str()));
+  for d in dets { draw_rect(img, d.x, d.y, d.w, d.h, colors[&d.label], t); }
+}
+
+pub fn draw_overlay(img: &mut RgbaImage, ov: &Overlay) {
+  let t = stroke(img);
+  draw_detections(img, &ov.dets);
+  for p in &ov.paths {
+    let c = p.color.unwrap_or(PATH_COLOR);
+    // each LOOP_BREAK-separated loop on its own, never a line out to the sentinel
+    for l in crate::measure::split_loops(&p.points) {
+      for w in l.windows(2) { draw_line(img, w[0], w[1], c, t); }
+      if p.closed && l.len() > 2 { draw_line(img, l[l.len() - 1], l[0], c, t); }
+    }
+  }
+}
+
+// blue → yellow → red ramp for t in [0,1]
+fn heat_color(t: f32) -> [u8; 3] {
+  let t = t.clamp(0.0, 1.0);
+  let (r, g, b) = if t < 0.5 { (t * 2.0, t * 2.0, 1.0 - t * 2.0) } else { (1.0, 2.0 - t * 2.0, 0.0) };
+  [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+}
+
+// normalized to the densest cell; empty cells are left untouched so the page reads through
+pub fn blend_heatmap(img: &mut RgbaImage, grid: &[Vec<u32>], cell: u32) {
+  let max = grid.iter().flatten().copied().max().unwrap_or(0);
+  if max == 0 { return; }
+  for (x, y, px) in img.enumerate_pixels_mut() {
+    let n = grid.get((y / cell) as usize).and_then(|r| r.get((x / cell) as usize)).copied().unwrap_or(0);
+    if n == 0 { continue; }
+    let t = n as f32 / max as f32;
+    let c = heat_color(t);
+    let a = 0.25 + 0.35 * t;
+    for k in 0..3 { px[k] = (px[k] as f32 * (1.0 - a) + c[k] as f32 * a) as u8; }
+  }
+}
+
+// page render with detection boxes in their label colours, as base64 PNG. Boxes are in pixels at
+// `dpi`; with a device_scale the render is that much larger and the boxes are scaled to match.
+#[tauri::command]
+pub async fn render_detection_overlay(pdf_path: String, page: u32, dpi: Option<u32>, dets: Vec<Det>, device_scale: Option<f32>) -> Result<String, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  let s = crate::transform::device_scale(dpi, device_scale)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| e.to_string())?;
+    let t = crate::transform::Transform::for_page(&doc, page, dpi)?.with_device_scale(s);
+    let mut img = crate::pdf::render_page(&doc, page, t.render_dpi())?.to_rgba8();
+    // the render rounds to a whole dpi, so scale by what it actually is
+    let k = t.render_dpi() as f32 / dpi as f32;
+    let dets: Vec<Det> = dets.into_iter().map(|d| Det { x: d.x * k, y: d.y * k, w: d.w * k, h: d.h * k, ..d }).collect();
+    draw_detections(&mut img, &dets);
+    encode_png_base64(img)
+  }).await.map_err(|e| e.to_string())?
+}
+
+pub fn encode_png_base64(img: RgbaImage) -> Result<String, String> {
+  use base64::Engine;
+  let mut png = Vec::new();
+  image::DynamicImage::ImageRgba8(img)
+    .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+    .map_err(|e| e.to_string())?;
+  Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
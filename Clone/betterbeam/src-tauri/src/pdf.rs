@@ -1,12 +1,315 @@
 use anyhow::Result;
 use pdfium_render::prelude::*;
 
+pub const PDFIUM_UNAVAILABLE: &str = "pdfium_unavailable";
+
+// the one place pdfium gets bound, once per process: every caller shares this binding and opens its
+// own PdfDocument. pdfium's init/destroy is process-wide, so a second Pdfium dropping mid-render
+// would tear the library down under the first. Failure is "pdfium_unavailable: ..." so the UI can
+// show a "PDF engine missing" screen instead of the raw loader error.
+static PDFIUM: once_cell::sync::OnceCell<(Pdfium, &'static str)> = once_cell::sync::OnceCell::new();
+
+fn binding() -> Result<&'static (Pdfium, &'static str), String> {
+  PDFIUM.get_or_try_init(|| bind_from(Pdfium::bind_to_system_library, Pdfium::bind_to_builtin_library))
+}
+
+pub fn bind() -> Result<&'static Pdfium, String> { binding().map(|b| &b.0) }
+
+// system library first, then the bundled one; split out so the failure path doesn't need a broken install
+fn bind_from<S, B>(system: S, builtin: B) -> Result<(Pdfium, &'static str), String>
+where
+  S: FnOnce() -> Result<Box<dyn PdfiumLibraryBindings>, PdfiumError>,
+  B: FnOnce() -> Result<Box<dyn PdfiumLibraryBindings>, PdfiumError>,
+{
+  let system_err = match system() { Ok(bindings) => return Ok((Pdfium::new(bindings), "system")), Err(e) => e };
+  match builtin() {
+    Ok(bindings) => Ok((Pdfium::new(bindings), "builtin")),
+    Err(e) => Err(format!(
+      "{PDFIUM_UNAVAILABLE}: the PDF engine could not be loaded (system library: {system_err}; bundled library: {e}). Reinstall BetterBeam, or put the pdfium library next to the executable or on the library path."
+    )),
+  }
+}
+
+// which library bind() uses: "system" or "builtin"
+pub fn bind_source() -> Result<&'static str, String> { binding().map(|b| b.1) }
+
+pub const EMPTY_DOCUMENT: &str = "empty_document";
+pub const PARSE_ERROR: &str = "parse_error";
+pub const INVALID_PAGE: &str = "invalid_page";
+
+// a load failure as "code: detail"; a missing file is reported as such, anything pdfium couldn't
+// make sense of (truncated, not a PDF, broken xref) is parse_error
+pub fn open_error(path: &str, e: PdfiumError) -> String {
+  if !std::path::Path::new(path).is_file() { return format!("{path}: file not found"); }
+  match e {
+    PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError) => format!("{path}: password required"),
+    e => format!("{PARSE_ERROR}: {path} could not be read as a PDF ({e})"),
+  }
+}
+
+// at least one page, or an error: empty_document for a valid file with none, parse_error for one that won't load.
+// Everything downstream assumes page 0 exists.
+pub fn page_count(path: &str) -> Result<u32, String> {
+  let lib = bind()?;
+  let doc = lib.load_pdf_from_file(path, None).map_err(|e| open_error(path, e))?;
+  match doc.pages().len() as u32 {
+    0 => Err(format!("{EMPTY_DOCUMENT}: {path} is a valid PDF with no pages")),
+    n => Ok(n),
+  }
+}
+
 pub async fn page_count_from_path(path: &str) -> Result<u32, String> {
-  let lib = Pdfium::new(
-    Pdfium::bind_to_system_library().or_else(|_| Pdfium::bind_to_builtin_library()).map_err(|e| e.to_string())?
-  );
-  let doc = lib.load_pdf_from_file(path, None).map_err(|e| e.to_string())?;
-  Ok(doc.pages().len() as u32)
+  page_count(path)
+}
+
+#[derive(Clone, serde::Serialize, Debug)]
+pub struct PdfValidation {
+  pub pages: u32,
+  // a user password is needed to open it; pages/scanned are unknown then
+  pub encrypted: bool,
+  // judged from the first page only, to stay quick
+  pub scanned: bool,
+  pub size_bytes: u64,
+}
+
+// cheap pre-flight: exists, has a PDF header, opens, and what kind of content it holds
+pub fn validate(path: &str) -> Result<PdfValidation, String> {
+  use std::io::Read;
+  let meta = std::fs::metadata(path).map_err(|e| format!("{path}: {e}"))?;
+  if !meta.is_file() { return Err(format!("{path} is not a file")); }
+  let mut head = [0u8; 1024];
+  let n = std::fs::File::open(path).and_then(|mut f| f.read(&mut head)).map_err(|e| format!("{path}: {e}"))?;
+  // the spec allows junk before the header, readers look within the first 1 KB
+  if !head[..n].windows(5).any(|w| w == b"%PDF-") { return Err(format!("{PARSE_ERROR}: {path} is not a PDF (no %PDF- header)")); }
+
+  let lib = bind()?;
+  let doc = match lib.load_pdf_from_file(path, None) {
+    Ok(doc) => doc,
+    Err(PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError)) => {
+      return Ok(PdfValidation { pages: 0, encrypted: true, scanned: false, size_bytes: meta.len() });
+    }
+    Err(e) => return Err(open_error(path, e)),
+  };
+  let pages = doc.pages().len() as u32;
+  let scanned = if pages == 0 { false } else {
+    let (w, h) = page_size(&doc, 0)?;
+    drop(doc);
+    is_scanned(&extract_images(path, 0)?, w, h)
+  };
+  Ok(PdfValidation { pages, encrypted: false, scanned, size_bytes: meta.len() })
+}
+
+#[tauri::command]
+pub async fn validate_pdf(path: String) -> Result<PdfValidation, String> {
+  validate(&path)
+}
+
+// an image placed on a page: decoded pixels plus where it sits, in page points (origin bottom-left)
+#[derive(Clone, Debug)]
+pub struct EmbeddedImage {
+  pub image: image::DynamicImage,
+  pub x: f32,
+  pub y: f32,
+  pub w: f32,
+  pub h: f32,
+  pub dpi_x: f32,
+  pub dpi_y: f32,
+}
+
+pub fn extract_images(pdf_path: &str, page: u32) -> Result<Vec<EmbeddedImage>, String> {
+  let lib = bind()?;
+  let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| open_error(pdf_path, e))?;
+  // checked here: past u16 the cast below would wrap onto some other page
+  let n = doc.pages().len() as u32;
+  if page >= n { return Err(format!("{INVALID_PAGE}: page {page} out of range (document has {n} pages)")); }
+  let page = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+  let mut out = Vec::new();
+  for obj in page.objects().iter() {
+    let Some(img) = obj.as_image_object() else { continue };
+    // skip images pdfium can't decode (e.g. unsupported filters) rather than failing the page
+    let Ok(raw) = img.get_raw_image() else { continue };
+    let Ok(b) = obj.bounds() else { continue };
+    let (w, h) = (b.width().value, b.height().value);
+    if w <= 0.0 || h <= 0.0 { continue; }
+    out.push(EmbeddedImage {
+      dpi_x: raw.width() as f32 * 72.0 / w,
+      dpi_y: raw.height() as f32 * 72.0 / h,
+      x: b.left().value, y: b.bottom().value, w, h,
+      image: raw,
+    });
+  }
+  Ok(out)
+}
+
+// true when placed images cover most of the page, i.e. a scan wrapped in a PDF
+pub fn is_scanned(images: &[EmbeddedImage], page_w: f32, page_h: f32) -> bool {
+  let page_area = (page_w * page_h).max(1.0);
+  images.iter().any(|i| i.w * i.h / page_area >= 0.8)
+}
+
+// native resolution of the dominant (largest placed) image, in image pixels per inch of page;
+// None for vector-only pages. Rendering above this only upscales the scan.
+pub fn effective_dpi(pdf_path: &str, page: u32) -> Result<Option<f32>, String> {
+  let images = extract_images(pdf_path, page)?;
+  Ok(images.iter()
+    .max_by(|a, b| (a.w * a.h).total_cmp(&(b.w * b.h)))
+    .map(|i| i.dpi_x.min(i.dpi_y)))
+}
+
+// requested dpi, lowered to the source resolution when the page is a raster scan
+pub fn cap_dpi(pdf_path: &str, page: u32, dpi: u32) -> u32 {
+  match effective_dpi(pdf_path, page) {
+    Ok(Some(native)) if native >= 1.0 => dpi.min(native.ceil() as u32),
+    _ => dpi,
+  }
+}
+
+#[tauri::command]
+pub async fn page_effective_dpi(pdf_path: String, page: u32) -> Result<Option<f32>, String> {
+  effective_dpi(&pdf_path, page)
+}
+
+const FINGERPRINT_DPI: u32 = 36;
+// fingerprints this close (of 64 bits) are the same sheet
+pub const DUPLICATE_DISTANCE: u32 = 6;
+
+// sets of pages that are the same drawing, each ascending; pages without a twin are left out
+pub fn duplicate_page_groups(pdf_path: &str) -> Result<Vec<Vec<u32>>, String> {
+  let lib = bind()?;
+  let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| e.to_string())?;
+  let mut groups: Vec<(u64, Vec<u32>)> = Vec::new();
+  for page in 0..doc.pages().len() as u32 {
+    let fp = crate::raster::page_fingerprint(&render_page(&doc, page, FINGERPRINT_DPI)?.to_luma8());
+    match groups.iter_mut().find(|(g, _)| (g ^ fp).count_ones() <= DUPLICATE_DISTANCE) {
+      Some((_, members)) => members.push(page),
+      None => groups.push((fp, vec![page])),
+    }
+  }
+  Ok(groups.into_iter().map(|(_, m)| m).filter(|m| m.len() > 1).collect())
+}
+
+#[tauri::command]
+pub async fn find_duplicate_pages(pdf_path: String) -> Result<Vec<Vec<u32>>, String> {
+  tauri::async_runtime::spawn_blocking(move || duplicate_page_groups(&pdf_path)).await.map_err(|e| e.to_string())?
 }
 
+pub const MAX_DPI: u32 = 1200;
+pub const DEFAULT_DPI: u32 = 150;
 
+pub fn check_dpi(dpi: u32) -> Result<(), String> {
+  if dpi == 0 || dpi > MAX_DPI { return Err(format!("dpi must be between 1 and {MAX_DPI}, got {dpi}")); }
+  Ok(())
+}
+
+// page size in points; pages are zero-based
+pub fn page_size(doc: &PdfDocument, page: u32) -> Result<(f32, f32), String> {
+  let n = doc.pages().len() as u32;
+  if page >= n { return Err(format!("page {page} out of range (document has {n} pages)")); }
+  let p = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+  Ok((p.width().value, p.height().value))
+}
+
+// how a page is rasterized. Antialiased output is for display; vectorization and detection want
+// hard edges that threshold cleanly.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct RenderFlags {
+  // RGBA painted under the page
+  pub background: [u8; 4],
+  pub antialias: bool,
+  pub grayscale: bool,
+}
+
+impl Default for RenderFlags {
+  fn default() -> Self { RenderFlags { background: [255, 255, 255, 255], antialias: true, grayscale: false } }
+}
+
+impl RenderFlags {
+  // crisp, binary-friendly render for the analysis stages
+  pub fn analysis() -> Self { RenderFlags { antialias: false, grayscale: true, ..Default::default() } }
+}
+
+pub fn render_page(doc: &PdfDocument, page: u32, dpi: u32) -> Result<image::DynamicImage, String> {
+  render_page_with(doc, page, dpi, &RenderFlags::default())
+}
+
+// pixel coordinates in the returned image follow crate::transform::Transform::for_page(doc, page, dpi)
+pub fn render_page_with(doc: &PdfDocument, page: u32, dpi: u32, flags: &RenderFlags) -> Result<image::DynamicImage, String> {
+  let t = crate::transform::Transform::for_page(doc, page, dpi)?;
+  let p = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+  let [r, g, b, a] = flags.background;
+  let cfg = PdfRenderConfig::new()
+    .scale_page_by_factor(t.scale())
+    .set_clear_color(PdfColor::new(r, g, b, a))
+    .use_grayscale_rendering(flags.grayscale)
+    .set_text_smoothing(flags.antialias)
+    .set_path_smoothing(flags.antialias)
+    .set_image_smoothing(flags.antialias);
+  Ok(p.render_with_config(&cfg).map_err(|e| e.to_string())?.as_image())
+}
+
+// only the (x, y, w, h) rectangle of render_page_with's image: the page is shifted so the rectangle's
+// corner sits at the bitmap origin and pdfium clips the rest, so a tile costs its own area, not the page's
+pub fn render_region_with(doc: &PdfDocument, page: u32, dpi: u32, flags: &RenderFlags, rect: (u32, u32, u32, u32)) -> Result<image::DynamicImage, String> {
+  let (x, y, w, h) = rect;
+  if w == 0 || h == 0 { return Err(format!("empty region {w}x{h}")); }
+  let t = crate::transform::Transform::for_page(doc, page, dpi)?;
+  let p = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+  let [r, g, b, a] = flags.background;
+  let cfg = PdfRenderConfig::new()
+    .scale_page_by_factor(t.scale())
+    .set_clear_color(PdfColor::new(r, g, b, a))
+    .use_grayscale_rendering(flags.grayscale)
+    .set_text_smoothing(flags.antialias)
+    .set_path_smoothing(flags.antialias)
+    .set_image_smoothing(flags.antialias)
+    // the offset is in page points; the page scale is applied after it
+    .translate(PdfPoints::new(-(x as f32) / t.scale()), PdfPoints::new(-(y as f32) / t.scale())).map_err(|e| e.to_string())?
+    .clip(0, 0, w as i32, h as i32);
+  let mut bitmap = PdfBitmap::empty(w as i32, h as i32, PdfBitmapFormat::BGRA, doc.bindings()).map_err(|e| e.to_string())?;
+  p.render_into_bitmap_with_config(&mut bitmap, &cfg).map_err(|e| e.to_string())?;
+  Ok(bitmap.as_image())
+}
+
+// one page as base64 PNG. With a device_scale the bitmap is that many times larger, for drawing at
+// the dpi's pixel size on a HiDPI display (see Transform::render_dpi).
+#[tauri::command]
+pub async fn render_page_image(pdf_path: String, page: u32, dpi: Option<u32>, flags: Option<RenderFlags>, device_scale: Option<f32>) -> Result<String, String> {
+  let dpi = dpi.unwrap_or(DEFAULT_DPI);
+  let s = crate::transform::device_scale(dpi, device_scale)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| e.to_string())?;
+    let t = crate::transform::Transform::for_page(&doc, page, dpi)?.with_device_scale(s);
+    let img = render_page_with(&doc, page, t.render_dpi(), &flags.unwrap_or_default())?;
+    crate::overlay::encode_png_base64(img.to_rgba8())
+  }).await.map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bind_failure_names_both_causes() {
+    let err = bind_from(|| Err(PdfiumError::UnrecognizedPath), || Err(PdfiumError::PageIndexOutOfBounds)).err().expect("both binds fail");
+    assert!(err.starts_with(PDFIUM_UNAVAILABLE), "{err}");
+    assert!(err.contains(&PdfiumError::UnrecognizedPath.to_string()), "{err}");
+    assert!(err.contains(&PdfiumError::PageIndexOutOfBounds.to_string()), "{err}");
+  }
+
+  #[test]
+  #[ignore = "needs pdfium: run with --ignored where the library is installed"]
+  fn truncated_file_is_a_parse_error() {
+    let path = std::env::temp_dir().join(format!("betterbeam-truncated-{}.pdf", std::process::id()));
+    std::fs::write(&path, b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R").unwrap();
+    let path = path.to_string_lossy().to_string();
+    let counted = page_count(&path);
+    let validated = validate(&path);
+    let _ = std::fs::remove_file(&path);
+    let err = counted.err().expect("truncated file must not open");
+    assert!(err.starts_with(PARSE_ERROR), "{err}");
+    let err = validated.err().expect("truncated file must not validate");
+    assert!(err.starts_with(PARSE_ERROR), "{err}");
+  }
+}
@@ -1,19 +1,21 @@
 use anyhow::{anyhow, Result};
-use std::{thread, time::Duration};
+use image::GrayImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, thread, time::Duration};
 
 // Placeholder types to allow incremental wiring; replace with real imports as you flesh out V5.
-struct GrayImage { w: u32, h: u32 }
-impl GrayImage { fn width(&self)->u32{self.w} fn height(&self)->u32{self.h} fn pixels(&self)->std::vec::IntoIter<[u8;1]>{vec![[0u8;1]].into_iter()} }
-struct PseudoLine { pub x0:f32, pub y0:f32, pub x1:f32, pub y1:f32 }
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PseudoLine { pub x0:f32, pub y0:f32, pub x1:f32, pub y1:f32 }
 struct LineDetectionOptions { pub vote_threshold: u32, pub suppression_radius: u32 }
 struct Lines; impl Lines { fn len(&self)->usize{0} }
 trait HasEndpoints { fn endpoints(&self)->((f32,f32),(f32,f32)); }
 fn detect_lines(_g:&GrayImage,_o:LineDetectionOptions)->Lines{Lines}
-fn canny(_g:&GrayImage,_l:f32,_h:f32)->GrayImage{GrayImage{w:1,h:1}}
-fn otsu_level(_g:&GrayImage)->u8{128}
+fn canny(g:&GrayImage,_l:f32,_h:f32)->GrayImage{GrayImage::new(g.width(),g.height())}
 fn merge_with_intersections(v:Vec<PseudoLine>)->Vec<PseudoLine>{v}
 
-fn retry_with_backoff<F, T>(mut f: F, max_retries: u32) -> Result<T>
+// `stop` is polled before each retry so a cancelled caller doesn't sit out the backoff
+pub fn retry_with_backoff<F, T>(mut f: F, max_retries: u32, stop: &dyn Fn() -> bool) -> Result<T>
 where
   F: FnMut() -> Result<T>,
 {
@@ -21,7 +23,7 @@ where
   loop {
     match f() {
       Ok(v) => return Ok(v),
-      Err(e) if attempts < max_retries => {
+      Err(_) if attempts < max_retries && !stop() => {
         attempts += 1;
         thread::sleep(Duration::from_millis(50 * (1u64 << attempts)));
       }
@@ -30,16 +32,60 @@ where
   }
 }
 
+// integer counts per chunk, summed in any order, so the parallel result is exact and repeatable
+pub fn histogram(gray: &GrayImage) -> [u64; 256] {
+  gray.as_raw()
+    .par_chunks(1 << 16)
+    .map(|c| { let mut h = [0u64; 256]; for &v in c { h[v as usize] += 1; } h })
+    .reduce(|| [0u64; 256], |mut a, b| { for (x, y) in a.iter_mut().zip(b) { *x += y; } a })
+}
+
+// dHash: shrink to 9x8 and record whether each pixel is brighter than its right neighbour.
+// Survives rescans, small shifts and re-rendering at another dpi; compare with Hamming distance.
+pub fn page_fingerprint(gray: &GrayImage) -> u64 {
+  let small = image::imageops::resize(gray, 9, 8, image::imageops::FilterType::Triangle);
+  let mut hash = 0u64;
+  for y in 0..8 {
+    for x in 0..8 {
+      hash = (hash << 1) | (small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]) as u64;
+    }
+  }
+  hash
+}
+
 fn grayscale_stats(gray: &GrayImage) -> (f32, f32) {
-  let mut sum = 0f64;
-  let mut sum2 = 0f64;
-  let n = (gray.width() as usize) * (gray.height() as usize);
-  for p in gray.pixels() { let v = p[0] as f64; sum += v; sum2 += v*v; }
-  let mean = (sum / n as f64) as f32;
-  let var = ((sum2 / n as f64) - (mean as f64).powi(2)).max(0.0) as f32;
+  let hist = histogram(gray);
+  let n: u64 = hist.iter().sum();
+  if n == 0 { return (0.0, 0.0); }
+  let sum: u64 = hist.iter().enumerate().map(|(v, &c)| v as u64 * c).sum();
+  let sum2: u64 = hist.iter().enumerate().map(|(v, &c)| (v * v) as u64 * c).sum();
+  let mean = (sum as f64 / n as f64) as f32;
+  let var = ((sum2 as f64 / n as f64) - (mean as f64).powi(2)).max(0.0) as f32;
   (mean, var.sqrt())
 }
 
+// Otsu's threshold: the level maximizing between-class variance of the histogram
+pub fn otsu_level(gray: &GrayImage) -> u8 {
+  let hist = histogram(gray);
+  let n: u64 = hist.iter().sum();
+  if n == 0 { return 128; }
+  let total: f64 = hist.iter().enumerate().map(|(v, &c)| v as f64 * c as f64).sum();
+  let (mut w0, mut sum0) = (0u64, 0f64);
+  let (mut best, mut best_var) = (0u8, -1f64);
+  for (t, &c) in hist.iter().enumerate() {
+    w0 += c;
+    if w0 == 0 { continue; }
+    let w1 = n - w0;
+    if w1 == 0 { break; }
+    sum0 += t as f64 * c as f64;
+    let (m0, m1) = (sum0 / w0 as f64, (total - sum0) / w1 as f64);
+    let var = w0 as f64 * w1 as f64 * (m0 - m1).powi(2);
+    if var > best_var { best_var = var; best = t as u8; }
+  }
+  // pixels <= best are the dark class; callers compare with `<`, so split just above it
+  best.saturating_add(1)
+}
+
 fn auto_tune_params(gray: &GrayImage, otsu: f32) -> (f32, f32, u32) {
   let (_mean, std) = grayscale_stats(gray);
   let factor = if std > 50.0 { 0.85 } else { 1.0 };
@@ -53,17 +99,786 @@ fn gpu_edges_if_big(_gray: &GrayImage, _dpi: u32) -> Option<GrayImage> {
   None
 }
 
-fn vectorize_gray(gray_in: &GrayImage, page_w_pt:f32, page_h_pt:f32) -> Vec<PseudoLine> {
-  let otsu = otsu_level(gray_in) as f32;
-  let (low, high, vote) = auto_tune_params(gray_in, otsu);
-  let edges_cpu = canny(gray_in, low, high);
-  let gray = gray_in;
-  let edges = gpu_edges_if_big(gray, 300).unwrap_or(edges_cpu);
-  let opts = LineDetectionOptions { vote_threshold: vote, suppression_radius: 6 };
-  let _lines = detect_lines(&edges, opts);
+#[derive(Clone, Debug, Default)]
+pub struct VectorizeOptions {
+  // white out text-like blobs first so labels don't turn into short segments
+  pub mask_text: bool,
+  // > 0: threshold at Otsu and close gaps up to about this many pixels (scans with broken strokes)
+  pub close_kernel: u32,
+}
+
+// what vectorize_gray actually ran with, after any sweep; enough to reproduce the result
+#[derive(Clone, Copy, Serialize, Debug)]
+pub struct VectorizeParams {
+  pub canny_low: f32,
+  pub canny_high: f32,
+  pub vote_threshold: u32,
+  // extra detection passes taken to get the line count into LINE_BAND
+  pub sweeps: u32,
+}
+
+// plausible line counts for one sheet; outside this the vote threshold was off for the drawing
+const LINE_BAND: std::ops::RangeInclusive<usize> = 50..=20_000;
+const MAX_SWEEPS: u32 = 4;
+
+fn vectorize_gray(gray_in: &GrayImage, page_w_pt:f32, page_h_pt:f32, vo: &VectorizeOptions) -> (Vec<PseudoLine>, VectorizeParams) {
+  let (edges, low, high, vote) = edge_stage(gray_in, vo);
+  line_stage(&edges, low, high, vote)
+}
+
+// preprocessing and edge map, plus the tuned canny thresholds and starting vote
+fn edge_stage(gray_in: &GrayImage, vo: &VectorizeOptions) -> (GrayImage, f32, f32, u32) {
+  let masked = vo.mask_text.then(|| mask_text(gray_in));
+  let gray = masked.as_ref().unwrap_or(gray_in);
+  let closed = (vo.close_kernel > 0).then(|| morph_close(&threshold(gray, otsu_level(gray)), vo.close_kernel));
+  let gray = closed.as_ref().unwrap_or(gray);
+  let otsu = otsu_level(gray) as f32;
+  let (low, high, vote) = auto_tune_params(gray, otsu);
+  let edges_cpu = canny(gray, low, high);
+  (gpu_edges_if_big(gray, 300).unwrap_or(edges_cpu), low, high, vote)
+}
+
+fn line_stage(edges: &GrayImage, low: f32, high: f32, mut vote: u32) -> (Vec<PseudoLine>, VectorizeParams) {
+  let mut sweeps = 0;
+  // too few lines: lower the vote bar; a flood: raise it. Bounded, and stops if the threshold can't move
+  let _lines = loop {
+    let lines = detect_lines(edges, LineDetectionOptions { vote_threshold: vote, suppression_radius: 6 });
+    let n = lines.len();
+    if LINE_BAND.contains(&n) || sweeps >= MAX_SWEEPS { break lines; }
+    let next = if n < *LINE_BAND.start() { (vote as f32 * 0.7) as u32 } else { (vote as f32 * 1.4).ceil() as u32 }.clamp(5, 500);
+    if next == vote { break lines; }
+    vote = next;
+    sweeps += 1;
+  };
   let mut segs = Vec::<PseudoLine>::new();
   // placeholder; convert _lines to segs and merge
-  merge_with_intersections(segs)
+  (merge_with_intersections(segs), VectorizeParams { canny_low: low, canny_high: high, vote_threshold: vote, sweeps })
 }
 
+// milliseconds over the timed iterations
+#[derive(Clone, Copy, Serialize, Debug)]
+pub struct Timing { pub min: f64, pub median: f64, pub max: f64 }
+
+impl Timing {
+  fn of(mut ms: Vec<f64>) -> Self {
+    ms.sort_by(f64::total_cmp);
+    let n = ms.len();
+    let median = if n % 2 == 1 { ms[n / 2] } else { (ms[n / 2 - 1] + ms[n / 2]) * 0.5 };
+    Timing { min: ms[0], median, max: ms[n - 1] }
+  }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct BenchResult {
+  pub iterations: u32,
+  pub dpi: u32,
+  pub render: Timing,
+  pub edges: Timing,
+  pub lines: Timing,
+  pub total: Timing,
+  pub lines_detected: usize,
+  pub params: VectorizeParams,
+}
+
+const MAX_BENCH_ITERATIONS: u32 = 100;
+
+// numbers to attach to a slowness report: render, edge and line stages of one page, timed separately
+// after one untimed warm-up. Only in debug builds, or when BETTERBEAM_ADVANCED is set.
+#[tauri::command]
+pub async fn benchmark_vectorize(pdf_path: String, page: u32, dpi: Option<u32>, iterations: Option<u32>) -> Result<BenchResult, String> {
+  if !cfg!(debug_assertions) && std::env::var_os("BETTERBEAM_ADVANCED").is_none() {
+    return Err("benchmark_vectorize is only available with BETTERBEAM_ADVANCED set".into());
+  }
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  let iterations = iterations.unwrap_or(5);
+  if !(1..=MAX_BENCH_ITERATIONS).contains(&iterations) { return Err(format!("iterations must be between 1 and {MAX_BENCH_ITERATIONS}, got {iterations}")); }
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let vo = VectorizeOptions::default();
+    let ms = |t: std::time::Instant| t.elapsed().as_secs_f64() * 1000.0;
+    let mut run = || -> Result<(f64, f64, f64, Vec<PseudoLine>, VectorizeParams), String> {
+      let t = std::time::Instant::now();
+      let gray = crate::pdf::render_page_with(&doc, page, dpi, &crate::pdf::RenderFlags::analysis())?.to_luma8();
+      let render = ms(t);
+      let t = std::time::Instant::now();
+      let (edges, low, high, vote) = edge_stage(&gray, &vo);
+      let edge = ms(t);
+      let t = std::time::Instant::now();
+      let (lines, params) = line_stage(&edges, low, high, vote);
+      Ok((render, edge, ms(t), lines, params))
+    };
+    run()?;
+    let (mut render, mut edges, mut lines, mut total) = (vec![], vec![], vec![], vec![]);
+    let mut last = None;
+    for _ in 0..iterations {
+      let (r, e, l, found, params) = run()?;
+      render.push(r);
+      edges.push(e);
+      lines.push(l);
+      total.push(r + e + l);
+      last = Some((found.len(), params));
+    }
+    let (lines_detected, params) = last.expect("at least one iteration");
+    Ok(BenchResult { iterations, dpi, render: Timing::of(render), edges: Timing::of(edges), lines: Timing::of(lines), total: Timing::of(total), lines_detected, params })
+  }).await.map_err(|e| e.to_string())?
+}
+
+
+
+// lines drawn in one colour only, so e.g. the red new work on a coloured set can be measured alone
+#[tauri::command]
+pub async fn vectorize_color_layer(pdf_path: String, page: u32, color: [u8; 3], dpi: Option<u32>) -> Result<Vec<PseudoLine>, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let t = crate::transform::Transform::for_page(&doc, page, dpi)?;
+    let (w, h) = t.pixel_size();
+    let rgb = crate::pdf::render_page_with(&doc, page, dpi, &crate::pdf::RenderFlags { antialias: false, ..Default::default() })?.to_rgb8();
+    let mask = split_color_layers(&rgb, &[image::Rgb(color)]).pop().expect("one mask per target");
+    Ok(vectorize_gray(&mask, w / t.scale(), h / t.scale(), &VectorizeOptions::default()).0)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// positions (row or column index) of strokes whose longest ink run spans at least `min_frac` of
+// the other dimension; adjacent hits from one thick stroke collapse to their middle
+fn long_strokes(gray: &GrayImage, horizontal: bool, min_frac: f32) -> Vec<f32> {
+  let (w, h) = gray.dimensions();
+  let (outer, inner) = if horizontal { (h, w) } else { (w, h) };
+  let need = (inner as f32 * min_frac) as u32;
+  let hits: Vec<u32> = (0..outer).into_par_iter().filter(|&o| {
+    let (mut run, mut best, mut gap) = (0u32, 0u32, false);
+    for i in 0..inner {
+      let (x, y) = if horizontal { (i, o) } else { (o, i) };
+      if gray.get_pixel(x, y)[0] < 128 {
+        run += 1 + gap as u32;
+        best = best.max(run);
+        gap = false;
+      } else if run > 0 && !gap {
+        // bridge a single-pixel break from scanning noise
+        gap = true;
+      } else {
+        run = 0;
+        gap = false;
+      }
+    }
+    best >= need
+  }).collect();
+  let mut out = Vec::new();
+  let mut i = 0;
+  while i < hits.len() {
+    let start = i;
+    while i + 1 < hits.len() && hits[i + 1] == hits[i] + 1 { i += 1; }
+    out.push((hits[start] + hits[i]) as f32 / 2.0);
+    i += 1;
+  }
+  out
+}
+
+// 0..1: how much the page looks like a schedule. Tables are many long rules at fairly even spacing
+// crossed by a few long columns; plans have long walls too, but at irregular spacing and fewer of them.
+pub fn table_confidence(gray: &GrayImage) -> f32 {
+  let (w, h) = gray.dimensions();
+  if w == 0 || h == 0 { return 0.0; }
+  let rows = long_strokes(gray, true, 0.4);
+  let cols = long_strokes(gray, false, 0.3);
+  if rows.len() < 4 || cols.len() < 2 { return 0.0; }
+  let gaps: Vec<f32> = rows.windows(2).map(|p| p[1] - p[0]).collect();
+  let mean = gaps.iter().sum::<f32>() / gaps.len() as f32;
+  let sd = (gaps.iter().map(|g| (g - mean).powi(2)).sum::<f32>() / gaps.len() as f32).sqrt();
+  let regularity = (1.0 - sd / mean.max(1.0)).clamp(0.0, 1.0);
+  let coverage = ((rows[rows.len() - 1] - rows[0]) / h as f32).clamp(0.0, 1.0);
+  let density = (rows.len() as f32 / 10.0).min(1.0) * (cols.len() as f32 / 3.0).min(1.0);
+  density * regularity * (coverage / 0.5).min(1.0)
+}
 
+pub fn is_table_page(gray: &GrayImage) -> bool { table_confidence(gray) >= 0.5 }
+
+// a graphic scale: the bar's horizontal extent and centre row, in pixels
+#[derive(Clone, Copy, Serialize, Debug)]
+pub struct ScaleBar { pub x0: f32, pub x1: f32, pub y: f32 }
+
+// longest run of horizontally continuous ink that's also a few pixels thick: the solid band of a
+// scale bar. Too-thin runs are border/grid lines; anything over ~4% of the page height is a fill.
+pub fn detect_bar_scale(gray: &GrayImage) -> Option<ScaleBar> {
+  let (w, h) = gray.dimensions();
+  let min_len = (w / 20).max(32);
+  let max_thick = (h / 25).max(4);
+  let dark = |x: u32, y: u32| gray.get_pixel(x, y)[0] < 128;
+  let mut best: Option<(u32, u32, u32)> = None;
+  for y in 0..h {
+    let mut x = 0;
+    while x < w {
+      if !dark(x, y) { x += 1; continue; }
+      let start = x;
+      while x < w && dark(x, y) { x += 1; }
+      let len = x - start;
+      if len < min_len || best.is_some_and(|(_, _, bl)| len <= bl) { continue; }
+      // thickness at the run's midpoint
+      let mid = start + len / 2;
+      let (mut top, mut bot) = (y, y);
+      while top > 0 && dark(mid, top - 1) { top -= 1; }
+      while bot + 1 < h && dark(mid, bot + 1) { bot += 1; }
+      let thick = bot - top + 1;
+      if (2..=max_thick).contains(&thick) { best = Some((start, (top + bot) / 2, len)); }
+    }
+  }
+  best.map(|(x0, y, len)| ScaleBar { x0: x0 as f32, x1: (x0 + len) as f32, y: y as f32 })
+}
+
+// share of a circle's outline that must be edge pixels to count
+const CIRCLE_MIN_COVERAGE: f32 = 0.5;
+
+// circle Hough over an edge map (non-zero = edge): (cx, cy, r) in pixels, strongest first. Rings
+// from both sides of a thick stroke, or neighbouring radii of one circle, collapse to the best.
+pub fn detect_circles(edges: &GrayImage, min_r: u32, max_r: u32) -> Vec<(f32, f32, f32)> {
+  let (w, h) = edges.dimensions();
+  let min_r = min_r.max(2);
+  if max_r < min_r || w == 0 || h == 0 { return vec![]; }
+  let pts: Vec<(i32, i32)> = edges.enumerate_pixels().filter(|(_, _, p)| p[0] > 0).map(|(x, y, _)| (x as i32, y as i32)).collect();
+  if pts.is_empty() { return vec![]; }
+
+  // per radius: accumulate centres, then keep the 3x3 local maxima that clear the coverage bar.
+  // One accumulator per worker, cleared between radii, rather than one per radius.
+  let mut found: Vec<(f32, f32, f32, f32)> = (min_r..=max_r).into_par_iter().map_init(|| vec![0u32; (w * h) as usize], |acc, r| {
+    let steps = ((std::f32::consts::TAU * r as f32).ceil() as usize).clamp(8, 1440);
+    let ring: Vec<(i32, i32)> = {
+      let mut v: Vec<(i32, i32)> = (0..steps).map(|i| {
+        let a = i as f32 / steps as f32 * std::f32::consts::TAU;
+        ((r as f32 * a.cos()).round() as i32, (r as f32 * a.sin()).round() as i32)
+      }).collect();
+      v.sort_unstable();
+      v.dedup();
+      v
+    };
+    acc.fill(0);
+    for &(x, y) in &pts {
+      for &(dx, dy) in &ring {
+        let (cx, cy) = (x - dx, y - dy);
+        if cx >= 0 && cy >= 0 && (cx as u32) < w && (cy as u32) < h { acc[(cy as u32 * w + cx as u32) as usize] += 1; }
+      }
+    }
+    let need = ((ring.len() as f32 * CIRCLE_MIN_COVERAGE).ceil() as u32).max(1);
+    let mut out = Vec::new();
+    for cy in 0..h as i32 {
+      for cx in 0..w as i32 {
+        let v = acc[(cy as u32 * w + cx as u32) as usize];
+        if v < need { continue; }
+        let peak = (-1..=1).all(|dy| (-1..=1).all(|dx| {
+          let (nx, ny) = (cx + dx, cy + dy);
+          if (dx, dy) == (0, 0) || nx < 0 || ny < 0 || nx as u32 >= w || ny as u32 >= h { return true; }
+          let n = acc[(ny as u32 * w + nx as u32) as usize];
+          // ties go to the first cell in scan order
+          n < v || (n == v && (dy, dx) > (0, 0))
+        }));
+        if peak { out.push((cx as f32, cy as f32, r as f32, v as f32 / ring.len() as f32)); }
+      }
+    }
+    out
+  }).flatten().collect();
+
+  found.sort_by(|a, b| b.3.total_cmp(&a.3).then(b.2.total_cmp(&a.2)));
+  let mut kept: Vec<(f32, f32, f32)> = Vec::new();
+  for (x, y, r, _) in found {
+    let dup = kept.iter().any(|&(kx, ky, kr)| {
+      let tol = (0.15 * kr.max(r)).max(2.0);
+      (kx - x).hypot(ky - y) <= tol && (kr - r).abs() <= tol
+    });
+    if !dup { kept.push((x, y, r)); }
+  }
+  kept
+}
+
+// the edge map is shrunk to at most this on its long side before voting; the accumulator is w×h
+// per worker and the work grows with edge pixels × radii, both of which shrink with it
+const CIRCLE_MAX_SIDE: u32 = 1024;
+
+// k×k blocks to one pixel, set if any edge pixel in the block is
+fn shrink_edges(edges: &GrayImage, k: u32) -> GrayImage {
+  let (w, h) = edges.dimensions();
+  let mut out = GrayImage::new(w.div_ceil(k), h.div_ceil(k));
+  for (x, y, p) in edges.enumerate_pixels() {
+    if p[0] > 0 { out.put_pixel(x / k, y / k, image::Luma([255])); }
+  }
+  out
+}
+
+// round things drawn on a page (columns, manholes, penetrations) with radii in [min_r, max_r] pixels
+pub fn circles_on_page(gray: &GrayImage, min_r: u32, max_r: u32) -> Vec<(f32, f32, f32)> {
+  let otsu = otsu_level(gray) as f32;
+  let (low, high, _) = auto_tune_params(gray, otsu);
+  let edges = canny(gray, low, high);
+  let k = gray.width().max(gray.height()).div_ceil(CIRCLE_MAX_SIDE).max(1);
+  if k == 1 { return detect_circles(&edges, min_r, max_r); }
+  // found on the small map, reported in page pixels (block centres)
+  let kf = k as f32;
+  detect_circles(&shrink_edges(&edges, k), min_r / k, max_r.div_ceil(k))
+    .into_iter().map(|(x, y, r)| ((x + 0.5) * kf, (y + 0.5) * kf, r * kf)).collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum HatchKind { Diagonal, Cross }
+
+const HATCH_CELL: u32 = 24;
+
+// classify one cell by how dark pixels continue: hatching runs along the diagonals, not the axes
+fn classify_hatch_cell(gray: &GrayImage, cx: u32, cy: u32) -> Option<HatchKind> {
+  let (w, h) = gray.dimensions();
+  let (x1, y1) = ((cx + HATCH_CELL).min(w), (cy + HATCH_CELL).min(h));
+  let dark = |x: u32, y: u32| x < w && y < h && gray.get_pixel(x, y)[0] < 128;
+  let (mut n, mut total) = (0u32, 0u32);
+  let (mut hz, mut vt, mut d1, mut d2) = (0u32, 0u32, 0u32, 0u32);
+  for y in cy..y1 {
+    for x in cx..x1 {
+      total += 1;
+      if !dark(x, y) { continue; }
+      n += 1;
+      if dark(x + 1, y) { hz += 1; }
+      if dark(x, y + 1) { vt += 1; }
+      if dark(x + 1, y + 1) { d1 += 1; }
+      if y > 0 && dark(x + 1, y - 1) { d2 += 1; }
+    }
+  }
+  // near-empty cells and solid fills are not hatching
+  let fill = n as f32 / total.max(1) as f32;
+  if !(0.04..=0.6).contains(&fill) { return None; }
+
+  // a hatch crosses the cell several times; count dark runs through the middle row and column
+  let runs = |pts: &mut dyn Iterator<Item = bool>| {
+    let (mut r, mut prev) = (0, false);
+    for d in pts { if d && !prev { r += 1; } prev = d; }
+    r
+  };
+  let (my, mx) = ((cy + y1) / 2, (cx + x1) / 2);
+  if runs(&mut (cx..x1).map(|x| dark(x, my))) < 2 || runs(&mut (cy..y1).map(|y| dark(mx, y))) < 2 { return None; }
+
+  let f = |c: u32| c as f32 / n as f32;
+  let (hz, vt, d1, d2) = (f(hz), f(vt), f(d1), f(d2));
+  let axis = hz.max(vt);
+  if d1.min(d2) >= 0.35 && d1 + d2 >= 0.8 && d1 + d2 > hz + vt { return Some(HatchKind::Cross); }
+  let diag = d1.max(d2);
+  if diag >= 0.7 && diag > axis * 1.3 { return Some(HatchKind::Diagonal); }
+  None
+}
+
+// outline of a set of grid cells as a closed loop of cell-corner coordinates (outer boundary only)
+fn trace_cell_outline(cells: &[(i32, i32)]) -> Vec<(i32, i32)> {
+  let set: std::collections::HashSet<(i32, i32)> = cells.iter().copied().collect();
+  let mut edges: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+  for &(i, j) in cells {
+    // clockwise in image space (y down), so loops keep a consistent winding
+    if !set.contains(&(i, j - 1)) { edges.entry((i, j)).or_default().push((i + 1, j)); }
+    if !set.contains(&(i + 1, j)) { edges.entry((i + 1, j)).or_default().push((i + 1, j + 1)); }
+    if !set.contains(&(i, j + 1)) { edges.entry((i + 1, j + 1)).or_default().push((i, j + 1)); }
+    if !set.contains(&(i - 1, j)) { edges.entry((i, j + 1)).or_default().push((i, j)); }
+  }
+  let mut best: Vec<(i32, i32)> = Vec::new();
+  let mut best_area = 0i64;
+  loop {
+    let Some(start) = edges.keys().next().copied() else { break };
+    let mut lp = vec![start];
+    let mut cur = start;
+    while let Some(next) = edges.get_mut(&cur).and_then(|v| v.pop()) {
+      if edges.get(&cur).is_some_and(|v| v.is_empty()) { edges.remove(&cur); }
+      cur = next;
+      if cur == start { break; }
+      lp.push(cur);
+    }
+    let area: i64 = (0..lp.len()).map(|k| {
+      let (a, b) = (lp[k], lp[(k + 1) % lp.len()]);
+      a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64
+    }).sum::<i64>().abs();
+    if area > best_area { best_area = area; best = lp; }
+  }
+  // drop collinear corners
+  let n = best.len();
+  (0..n).filter(|&k| {
+    let (a, b, c) = (best[(k + n - 1) % n], best[k], best[(k + 1) % n]);
+    (b.0 - a.0) * (c.1 - b.1) != (b.1 - a.1) * (c.0 - b.0)
+  }).map(|k| best[k]).collect()
+}
+
+pub fn detect_hatch_regions_with_kind(gray: &GrayImage) -> Vec<(HatchKind, Vec<(f32, f32)>)> {
+  let (w, h) = gray.dimensions();
+  let (gw, gh) = (w.div_ceil(HATCH_CELL) as i32, h.div_ceil(HATCH_CELL) as i32);
+  let mut kinds: HashMap<(i32, i32), HatchKind> = HashMap::new();
+  for j in 0..gh {
+    for i in 0..gw {
+      if let Some(k) = classify_hatch_cell(gray, i as u32 * HATCH_CELL, j as u32 * HATCH_CELL) { kinds.insert((i, j), k); }
+    }
+  }
+  let mut out = Vec::new();
+  let mut seen = std::collections::HashSet::new();
+  let mut keys: Vec<(i32, i32)> = kinds.keys().copied().collect();
+  keys.sort();
+  for seed in keys {
+    if !seen.insert(seed) { continue; }
+    let kind = kinds[&seed];
+    let (mut comp, mut stack) = (vec![], vec![seed]);
+    while let Some((i, j)) = stack.pop() {
+      comp.push((i, j));
+      for nb in [(i + 1, j), (i - 1, j), (i, j + 1), (i, j - 1)] {
+        if kinds.get(&nb) == Some(&kind) && seen.insert(nb) { stack.push(nb); }
+      }
+    }
+    // a lone cell is as likely to be a bit of text or a dense symbol
+    if comp.len() < 2 { continue; }
+    let poly = trace_cell_outline(&comp).into_iter()
+      .map(|(i, j)| (((i as u32) * HATCH_CELL).min(w) as f32, ((j as u32) * HATCH_CELL).min(h) as f32))
+      .collect();
+    out.push((kind, poly));
+  }
+  out
+}
+
+pub fn detect_hatch_regions(gray: &GrayImage) -> Vec<Vec<(f32, f32)>> {
+  detect_hatch_regions_with_kind(gray).into_iter().map(|(_, p)| p).collect()
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct HatchRegion {
+  pub kind: HatchKind,
+  // closed outline in pixels at the render dpi, ready to become an area measurement
+  pub points: Vec<(f32, f32)>,
+}
+
+// hatched areas on a page, offered as seeds for area takeoff
+#[tauri::command]
+pub async fn hatch_area_seeds(pdf_path: String, page: u32, dpi: Option<u32>) -> Result<Vec<HatchRegion>, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let lib = crate::pdf::bind()?;
+    let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+    let gray = crate::pdf::render_page_with(&doc, page, dpi, &crate::pdf::RenderFlags::analysis())?.to_luma8();
+    Ok(detect_hatch_regions_with_kind(&gray).into_iter().map(|(kind, points)| HatchRegion { kind, points }).collect())
+  }).await.map_err(|e| e.to_string())?
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct Leader {
+  pub tip: (f32, f32),
+  pub tail: (f32, f32),
+  // tip → tail, simplified; more than two points for kinked leaders
+  pub points: Vec<(f32, f32)>,
+}
+
+fn rdp(points: &[(f32, f32)], eps: f32) -> Vec<(f32, f32)> {
+  if points.len() < 3 { return points.to_vec(); }
+  let (a, b) = (points[0], points[points.len() - 1]);
+  let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+  let len = (dx * dx + dy * dy).sqrt().max(1e-6);
+  let (mut idx, mut dmax) = (0, 0.0f32);
+  for (i, p) in points.iter().enumerate().take(points.len() - 1).skip(1) {
+    let d = ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / len;
+    if d > dmax { dmax = d; idx = i; }
+  }
+  if dmax <= eps { return vec![a, b]; }
+  let mut left = rdp(&points[..=idx], eps);
+  left.pop();
+  left.extend(rdp(&points[idx..], eps));
+  left
+}
+
+// arrowheads are small filled blobs that survive an erosion thin strokes don't; from each one,
+// walk the attached stroke to its far end to get the tail
+pub fn detect_leaders(gray: &GrayImage) -> Vec<Leader> {
+  let (w, h) = gray.dimensions();
+  let (wi, hi) = (w as i32, h as i32);
+  let dark = |x: i32, y: i32| x >= 0 && y >= 0 && x < wi && y < hi && gray.get_pixel(x as u32, y as u32)[0] < 128;
+  let nb8 = |x: i32, y: i32| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)));
+  let idx = |x: i32, y: i32| (y * wi + x) as usize;
+
+  let mut thick = vec![false; (w * h) as usize];
+  for y in 0..hi { for x in 0..wi { thick[idx(x, y)] = nb8(x, y).all(|(a, b)| dark(a, b)); } }
+
+  let mut seen = vec![false; thick.len()];
+  let mut out = Vec::new();
+  for y0 in 0..hi {
+    for x0 in 0..wi {
+      if !thick[idx(x0, y0)] || seen[idx(x0, y0)] { continue; }
+      let (mut blob, mut stack) = (vec![], vec![(x0, y0)]);
+      seen[idx(x0, y0)] = true;
+      while let Some((x, y)) = stack.pop() {
+        blob.push((x, y));
+        for (a, b) in nb8(x, y) {
+          if a >= 0 && b >= 0 && a < wi && b < hi && thick[idx(a, b)] && !seen[idx(a, b)] { seen[idx(a, b)] = true; stack.push((a, b)); }
+        }
+      }
+      let (bx0, bx1) = blob.iter().fold((i32::MAX, i32::MIN), |m, p| (m.0.min(p.0), m.1.max(p.0)));
+      let (by0, by1) = blob.iter().fold((i32::MAX, i32::MIN), |m, p| (m.0.min(p.1), m.1.max(p.1)));
+      let size = (bx1 - bx0).max(by1 - by0) + 1;
+      if !(4..=150).contains(&blob.len()) || size > 20 { continue; }
+
+      // BFS along thin dark pixels leaving the blob; the geodesically farthest pixel is the tail
+      let mut parent: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+      let mut q = std::collections::VecDeque::new();
+      for &p in &blob { parent.insert(p, p); q.push_back(p); }
+      let mut last = blob[0];
+      while let Some((x, y)) = q.pop_front() {
+        last = (x, y);
+        if parent.len() > 20_000 { break; }
+        for (a, b) in nb8(x, y) {
+          if dark(a, b) && !thick[idx(a, b)] && !parent.contains_key(&(a, b)) { parent.insert((a, b), (x, y)); q.push_back((a, b)); }
+        }
+      }
+      let mut path = vec![last];
+      let mut cur = last;
+      while parent[&cur] != cur { cur = parent[&cur]; path.push(cur); }
+      if (path.len() as i32) < size * 3 { continue; }
+
+      // the arrow point is the blob pixel farthest from where the stroke attaches
+      let attach = *path.last().unwrap();
+      let tip = *blob.iter().max_by_key(|p| (p.0 - attach.0).pow(2) + (p.1 - attach.1).pow(2)).unwrap();
+      path.reverse();
+      let mut pts: Vec<(f32, f32)> = std::iter::once(tip).chain(path).map(|(x, y)| (x as f32, y as f32)).collect();
+      pts.dedup();
+      let pts = rdp(&pts, 1.5);
+      out.push(Leader { tip: pts[0], tail: *pts.last().unwrap(), points: pts });
+    }
+  }
+  out
+}
+
+const TEXT_MAX_PX: u32 = 40;
+
+// connected dark blobs that are small in both directions and not dash-shaped are taken as glyphs;
+// anything long (walls, dimension lines) is left alone however thin it is
+pub fn mask_text(gray: &GrayImage) -> GrayImage {
+  let (w, h) = gray.dimensions();
+  let mut out = gray.clone();
+  let mut seen = vec![false; (w * h) as usize];
+  let dark = |x: u32, y: u32| gray.get_pixel(x, y)[0] < 128;
+  for y0 in 0..h {
+    for x0 in 0..w {
+      let i0 = (y0 * w + x0) as usize;
+      if seen[i0] || !dark(x0, y0) { continue; }
+      seen[i0] = true;
+      let (mut comp, mut stack) = (Vec::new(), vec![(x0, y0)]);
+      let (mut bx0, mut by0, mut bx1, mut by1) = (x0, y0, x0, y0);
+      while let Some((x, y)) = stack.pop() {
+        (bx0, by0, bx1, by1) = (bx0.min(x), by0.min(y), bx1.max(x), by1.max(y));
+        // stop collecting once it's clearly linework; the fill still runs to mark it seen
+        if bx1 - bx0 <= TEXT_MAX_PX && by1 - by0 <= TEXT_MAX_PX { comp.push((x, y)); }
+        for dy in -1i32..=1 {
+          for dx in -1i32..=1 {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 { continue; }
+            let i = (ny as u32 * w + nx as u32) as usize;
+            if !seen[i] && dark(nx as u32, ny as u32) { seen[i] = true; stack.push((nx as u32, ny as u32)); }
+          }
+        }
+      }
+      let (bw, bh) = (bx1 - bx0 + 1, by1 - by0 + 1);
+      if bw > TEXT_MAX_PX || bh > TEXT_MAX_PX { continue; }
+      let aspect = bw.max(bh) as f32 / bw.min(bh) as f32;
+      if aspect > 6.0 { continue; }
+      for (x, y) in comp { out.put_pixel(x, y, image::Luma([255])); }
+    }
+  }
+  out
+}
+
+// ink (below `level`) becomes 0, paper 255
+pub fn threshold(gray: &GrayImage, level: u8) -> GrayImage {
+  let mut out = gray.clone();
+  for p in out.pixels_mut() { p[0] = if p[0] < level { 0 } else { 255 }; }
+  out
+}
+
+// separable square min/max filter of width `k`; `dark_grows` spreads ink (min), otherwise paper (max)
+fn square_filter(img: &GrayImage, k: u32, dark_grows: bool) -> GrayImage {
+  let (w, h) = img.dimensions();
+  let r = (k / 2) as i64;
+  let pick = |a: u8, b: u8| if dark_grows { a.min(b) } else { a.max(b) };
+  let run = |src: &GrayImage, horizontal: bool| {
+    let mut out = src.clone();
+    for y in 0..h as i64 {
+      for x in 0..w as i64 {
+        let mut v = src.get_pixel(x as u32, y as u32)[0];
+        for d in -r..=r {
+          let (sx, sy) = if horizontal { (x + d, y) } else { (x, y + d) };
+          if sx < 0 || sy < 0 || sx >= w as i64 || sy >= h as i64 { continue; }
+          v = pick(v, src.get_pixel(sx as u32, sy as u32)[0]);
+        }
+        out.put_pixel(x as u32, y as u32, image::Luma([v]));
+      }
+    }
+    out
+  };
+  run(&run(img, true), false)
+}
+
+// dilate then erode the ink, bridging gaps narrower than the kernel without fattening strokes
+pub fn morph_close(bw: &GrayImage, kernel: u32) -> GrayImage {
+  if kernel < 2 { return bw.clone(); }
+  square_filter(&square_filter(bw, kernel, true), kernel, false)
+}
+
+// euclidean RGB distance that still counts as the target colour: wide enough for antialiased
+// edges and print-to-PDF colour shifts, narrow enough that red and magenta stay apart
+pub const COLOR_LAYER_TOLERANCE: f32 = 60.0;
+
+// one mask per target colour: pixels within COLOR_LAYER_TOLERANCE become ink (0), the rest
+// paper (255), so each mask can go straight through vectorize/measure
+pub fn split_color_layers(img: &image::RgbImage, targets: &[image::Rgb<u8>]) -> Vec<GrayImage> {
+  let (w, h) = img.dimensions();
+  let tol2 = COLOR_LAYER_TOLERANCE * COLOR_LAYER_TOLERANCE;
+  let mut masks: Vec<GrayImage> = targets.iter().map(|_| GrayImage::from_pixel(w, h, image::Luma([255]))).collect();
+  for (x, y, p) in img.enumerate_pixels() {
+    for (t, m) in targets.iter().zip(masks.iter_mut()) {
+      let d2: f32 = (0..3).map(|k| (p[k] as f32 - t[k] as f32).powi(2)).sum();
+      if d2 <= tol2 { m.put_pixel(x, y, image::Luma([0])); }
+    }
+  }
+  masks
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn paper(w: u32, h: u32) -> GrayImage { GrayImage::from_pixel(w, h, image::Luma([255])) }
+
+  // ink over the inclusive box
+  fn ink(img: &mut GrayImage, x0: u32, y0: u32, x1: u32, y1: u32) {
+    for y in y0..=y1 { for x in x0..=x1 { img.put_pixel(x, y, image::Luma([0])); } }
+  }
+
+  #[test]
+  fn mask_text_clears_glyphs_and_keeps_lines() {
+    let mut img = paper(240, 120);
+    // a paragraph of 5x8 "letters" two pixels apart
+    for row in 0..3 {
+      for col in 0..12 { let (x, y) = (20 + col * 7, 20 + row * 12); ink(&mut img, x, y, x + 4, y + 7); }
+    }
+    ink(&mut img, 10, 80, 230, 81);
+    ink(&mut img, 200, 5, 201, 70);
+    let out = mask_text(&img);
+    for row in 0..3 {
+      for col in 0..12 { assert_eq!(out.get_pixel(22 + col * 7, 24 + row * 12)[0], 255); }
+    }
+    for x in (10..=230).step_by(10) { assert_eq!(out.get_pixel(x, 80)[0], 0); }
+    for y in (5..=70).step_by(5) { assert_eq!(out.get_pixel(200, y)[0], 0); }
+  }
+
+  #[test]
+  fn morph_close_bridges_a_one_pixel_gap() {
+    let mut img = paper(60, 20);
+    ink(&mut img, 5, 10, 24, 10);
+    ink(&mut img, 26, 10, 45, 10);
+    let out = morph_close(&img, 3);
+    for x in 5..=45 { assert_eq!(out.get_pixel(x, 10)[0], 0, "gap left at x={x}"); }
+    // closing doesn't thicken the stroke
+    assert_eq!(out.get_pixel(25, 8)[0], 255);
+    assert_eq!(out.get_pixel(25, 12)[0], 255);
+  }
+
+  #[test]
+  fn gridded_table_is_a_table_page() {
+    let mut img = paper(400, 300);
+    for i in 0..12 { let y = 20 + i * 23; ink(&mut img, 20, y, 380, y); }
+    for x in [20, 120, 260, 380] { ink(&mut img, x, 20, x, 273); }
+    assert!(is_table_page(&img), "confidence {}", table_confidence(&img));
+  }
+
+  #[test]
+  fn sparse_plan_is_not_a_table_page() {
+    let mut img = paper(400, 300);
+    // outer walls, two interior walls at uneven spacing, a door swing's worth of short strokes
+    ink(&mut img, 20, 20, 380, 21);
+    ink(&mut img, 20, 278, 380, 279);
+    ink(&mut img, 20, 20, 21, 279);
+    ink(&mut img, 378, 20, 379, 279);
+    ink(&mut img, 20, 95, 240, 96);
+    ink(&mut img, 240, 95, 241, 279);
+    for i in 0..6 { ink(&mut img, 60 + i * 9, 150 + i * 7, 66 + i * 9, 152 + i * 7); }
+    assert!(!is_table_page(&img), "confidence {}", table_confidence(&img));
+  }
+
+  // one-pixel outline as an edge map (non-zero = edge)
+  fn ring(img: &mut GrayImage, cx: f32, cy: f32, r: f32) {
+    for i in 0..720 {
+      let a = i as f32 / 720.0 * std::f32::consts::TAU;
+      img.put_pixel((cx + r * a.cos()).round() as u32, (cy + r * a.sin()).round() as u32, image::Luma([255]));
+    }
+  }
+
+  #[test]
+  fn detect_circles_recovers_centres_and_radii() {
+    let mut edges = GrayImage::new(140, 100);
+    ring(&mut edges, 35.0, 40.0, 10.0);
+    ring(&mut edges, 95.0, 55.0, 22.0);
+    let found = detect_circles(&edges, 5, 30);
+    for (cx, cy, r) in [(35.0, 40.0, 10.0), (95.0, 55.0, 22.0)] {
+      assert!(found.iter().any(|&(x, y, fr)| (x - cx).hypot(y - cy) <= 1.5 && (fr - r).abs() <= 1.5), "no circle near ({cx}, {cy}) r={r} in {found:?}");
+    }
+  }
+
+  #[test]
+  fn shrunk_edges_keep_every_block_with_an_edge() {
+    let mut edges = GrayImage::new(10, 7);
+    edges.put_pixel(9, 6, image::Luma([255]));
+    edges.put_pixel(0, 0, image::Luma([255]));
+    let small = shrink_edges(&edges, 4);
+    assert_eq!(small.dimensions(), (3, 2));
+    assert_eq!(small.get_pixel(2, 1)[0], 255);
+    assert_eq!(small.get_pixel(0, 0)[0], 255);
+    assert_eq!(small.pixels().filter(|p| p[0] > 0).count(), 2);
+  }
+
+  #[test]
+  fn kinked_leader_keeps_its_bend() {
+    let mut img = paper(140, 140);
+    // arrowhead, then a hairline right and down
+    ink(&mut img, 20, 50, 26, 56);
+    ink(&mut img, 27, 53, 90, 53);
+    ink(&mut img, 90, 53, 90, 110);
+    let leaders = detect_leaders(&img);
+    assert_eq!(leaders.len(), 1);
+    let l = &leaders[0];
+    assert!(l.tip.0 <= 22.0, "{:?}", l.tip);
+    assert!((l.tail.0 - 90.0).abs() <= 1.0 && (l.tail.1 - 110.0).abs() <= 1.0, "{:?}", l.tail);
+    assert!(l.points.len() >= 3);
+    assert!(l.points.iter().any(|p| (p.0 - 90.0).hypot(p.1 - 53.0) <= 3.0), "{:?}", l.points);
+  }
+
+  #[test]
+  fn hatch_kinds_are_told_apart_and_solid_fill_is_skipped() {
+    // four cells each of diagonal hatch, crosshatch and solid black, a blank cell between them
+    let mut img = paper(336, 96);
+    for y in 0..96u32 {
+      for x in 0..96u32 {
+        if (x + y) % 8 == 0 { img.put_pixel(x, y, image::Luma([0])); }
+        if (x + y) % 8 == 0 || (x + 8 - y % 8) % 8 == 0 { img.put_pixel(x + 120, y, image::Luma([0])); }
+      }
+    }
+    ink(&mut img, 240, 0, 335, 95);
+    let mut regions = detect_hatch_regions_with_kind(&img);
+    regions.sort_by(|a, b| a.1[0].0.total_cmp(&b.1[0].0));
+    let kinds: Vec<HatchKind> = regions.iter().map(|r| r.0).collect();
+    assert_eq!(kinds, vec![HatchKind::Diagonal, HatchKind::Cross]);
+    assert!(regions[0].1.iter().all(|p| p.0 <= 96.0));
+    assert!(regions[1].1.iter().all(|p| (120.0..=216.0).contains(&p.0)));
+  }
+
+  #[test]
+  fn red_and_blue_ink_land_in_their_own_masks() {
+    let mut img = image::RgbImage::from_pixel(40, 20, image::Rgb([255, 255, 255]));
+    let (red, blue) = (image::Rgb([220, 30, 30]), image::Rgb([20, 40, 230]));
+    for x in 0..40 { img.put_pixel(x, 5, red); img.put_pixel(x, 15, blue); }
+    // an antialiased edge pixel, still close enough to red
+    img.put_pixel(0, 6, image::Rgb([245, 35, 35]));
+    let masks = split_color_layers(&img, &[image::Rgb([255, 0, 0]), image::Rgb([0, 0, 255])]);
+    assert_eq!(masks.len(), 2);
+    let inked = |m: &GrayImage| m.enumerate_pixels().filter(|p| p.2[0] == 0).map(|p| (p.0, p.1)).collect::<Vec<_>>();
+    let (r, b) = (inked(&masks[0]), inked(&masks[1]));
+    assert_eq!(r.len(), 41);
+    assert!(r.iter().all(|p| p.1 == 5 || *p == (0, 6)));
+    assert_eq!(b.len(), 40);
+    assert!(b.iter().all(|p| p.1 == 15));
+  }
+}
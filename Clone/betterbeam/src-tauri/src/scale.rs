@@ -1,25 +1,419 @@
-// very small parser for common scale strings; returns (units, units_per_pixel) when possible
-pub fn infer_scale_from_text(text: String) -> Option<(String, f32)> {
-  let t = text.to_lowercase();
-  // handle metric 1:100, 1:50
-  if let Some(pos) = t.find("1:") {
-    let rest = &t[pos+2..];
-    let mut num = String::new();
-    for ch in rest.chars() { if ch.is_ascii_digit() { num.push(ch); } else { break; } }
-    if let Ok(n) = num.parse::<f32>() { if n > 0.0 { return Some(("m".into(), 1.0 / n)); } }
-  }
-  // handle imperial like 1/8" = 1'-0"
-  if let Some(eq) = t.find('=') {
-    let left = t[..eq].trim();
-    if let Some(slash) = left.find('/') {
-      let (a, b) = left.split_at(slash);
-      let b = &b[1..];
-      if let (Ok(na), Ok(nb)) = (a.trim().parse::<f32>(), b.trim_matches(['"',' ']).parse::<f32>()) {
-        if nb > 0.0 { return Some(("ft".into(), (na/nb) /* inches per foot */ * (1.0/12.0))); }
+// where in the text a scale was read from, so the user can tell it from a date or a note
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
+pub struct ScaleMatch {
+  pub units: String,
+  pub units_per_pixel: f32,
+  // as printed, e.g. "1:100" or "1/8\" = 1'-0\""
+  pub matched: String,
+  // "metric_ratio" or "imperial_fraction"
+  pub pattern: String,
+  // in characters from the start of the searched text
+  pub offset: usize,
+}
+
+fn char_offset(text: &str, byte: usize) -> usize { text[..byte].chars().count() }
+
+// drawings are drawn between 1:5 and 1:2000
+const RATIO_RANGE: std::ops::RangeInclusive<f32> = 5.0..=2000.0;
+
+// words that put a nearby "1:n" beyond doubt
+const SCALE_WORDS: &[&str] = &["scale", "esc", "maßstab", "echelle"];
+
+// "1:n" found at text[start..end]: a drawing ratio in range, not part of a larger number ("11:30"),
+// and, unless a scale word comes shortly before it, not reading like a time ("at 1:30", "1:30 pm", "1:30:00")
+fn plausible_ratio(text: &str, start: usize, end: usize, n: f32) -> bool {
+  if !RATIO_RANGE.contains(&n) { return false; }
+  if text[..start].chars().next_back().is_some_and(|c| c.is_ascii_digit() || c == '.') { return false; }
+  let before = text[..start].to_lowercase();
+  let near: String = before.chars().rev().take(24).collect::<Vec<_>>().into_iter().rev().collect();
+  if SCALE_WORDS.iter().any(|w| near.contains(w)) { return true; }
+  let after = text[end..].trim_start().to_lowercase();
+  let prev_word = before.split_whitespace().next_back().unwrap_or("");
+  let time_like = after.starts_with("am") || after.starts_with("pm") || text[end..].starts_with(':')
+    || matches!(prev_word, "at" | "@" | "by" | "until" | "from");
+  !time_like
+}
+
+// very small parser for common scale strings. The printed ratio is paper to real; `dpi` is the render
+// the page is measured on, so units_per_pixel comes out in real units (m or ft) per pixel of it.
+pub fn match_scale_text(text: &str, dpi: u32) -> Option<ScaleMatch> {
+  let paper_in_per_px = 1.0 / dpi.max(1) as f32;
+  // handle metric 1:100, 1:50; the first "1:" that passes plausible_ratio
+  for (pos, _) in text.match_indices("1:") {
+    let num: String = text[pos + 2..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    let Ok(n) = num.parse::<f32>() else { continue };
+    if !plausible_ratio(text, pos, pos + 2 + num.len(), n) { continue; }
+    // a pixel is 0.0254/dpi m of paper, n times that for real
+    return Some(ScaleMatch { units: "m".into(), units_per_pixel: n * 0.0254 * paper_in_per_px, matched: format!("1:{num}"), pattern: "metric_ratio".into(), offset: char_offset(text, pos) });
+  }
+  // handle imperial like 1/8" = 1'-0"; the fraction is the last word before the '='
+  if let Some(eq) = text.find('=') {
+    let left = text[..eq].trim_end();
+    let start = left.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+    if let Some((a, b)) = left[start..].split_once('/') {
+      if let (Ok(na), Ok(nb)) = (a.trim().parse::<f32>(), b.trim_matches(['"', ' ']).parse::<f32>()) {
+        let rhs_at = eq + 1 + (text[eq + 1..].len() - text[eq + 1..].trim_start().len());
+        // the longest run of up to four words after the '=' that reads as a length ("1'-6\"", "1 ft 6 in")
+        let rest = &text[rhs_at..];
+        let ends: Vec<usize> = rest.char_indices()
+          .filter(|&(i, c)| !c.is_whitespace() && rest[i + c.len_utf8()..].chars().next().is_none_or(char::is_whitespace))
+          .map(|(i, c)| rhs_at + i + c.len_utf8())
+          .take(4)
+          .collect();
+        let rhs = ends.iter().rev().find_map(|&end| parse_feet_inches(&text[rhs_at..end]).map(|ft| (end, ft)));
+        if let (true, Some((end, feet))) = (nb > 0.0, rhs) {
+          return Some(ScaleMatch {
+            units: "ft".into(),
+            // na/nb paper inches stand for `feet` real feet
+            units_per_pixel: feet as f32 / (na / nb) * paper_in_per_px,
+            matched: text[start..end].to_string(),
+            pattern: "imperial_fraction".into(),
+            offset: char_offset(text, start),
+          });
+        }
       }
     }
   }
   None
 }
 
+// (units, units_per_pixel) when possible; see match_scale_text for where it was found
+pub fn infer_scale_from_text(text: String, dpi: u32) -> Option<(String, f32)> {
+  match_scale_text(&text, dpi).map(|m| (m.units, m.units_per_pixel))
+}
+
+// "6", "6.5", "1/2", "6 1/2", "6-1/2"
+fn parse_mixed_number(s: &str) -> Option<f64> {
+  let s = s.trim();
+  if s.is_empty() { return None; }
+  let frac = |f: &str| {
+    let (a, b) = f.split_once('/')?;
+    let (a, b) = (a.trim().parse::<f64>().ok()?, b.trim().parse::<f64>().ok()?);
+    (b > 0.0).then_some(a / b)
+  };
+  if let Some((whole, rest)) = s.split_once([' ', '-']).filter(|(_, r)| r.contains('/')) {
+    return Some(whole.trim().parse::<f64>().ok()? + frac(rest)?);
+  }
+  if s.contains('/') { return frac(s); }
+  s.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+// total feet from an imperial length: 1'-6", 1' 6", 1'6", 1', 18", 1.5', 1 ft, 1 ft 6 in.
+// Metric and bare numbers give None; see parse_dimension for the shared fraction handling.
+pub fn parse_feet_inches(s: &str) -> Option<f64> {
+  let mut t = s.trim().to_lowercase();
+  for (word, mark) in [("feet", "'"), ("foot", "'"), ("ft", "'"), ("inches", "\""), ("inch", "\""), ("in", "\"")] {
+    t = t.replace(word, mark);
+  }
+  let t = t.replace(" '", "'").replace(" \"", "\"");
+  if !t.ends_with(['\'', '"', '′', '″', '’', '”']) { return None; }
+  parse_dimension(&t)
+}
+
+// a printed dimension as a length in feet: 24'-6", 24' 6 1/2", 24', 6", 1200 mm, 3.5 m.
+// Bare numbers have no unit and give None.
+pub fn parse_dimension(text: &str) -> Option<f64> {
+  let t = text.trim().to_lowercase().replace(['′', '’'], "'").replace(['″', '”'], "\"").replace("''", "\"");
+  let v = if let Some(n) = t.strip_suffix("mm") { parse_mixed_number(n)? / 304.8 }
+    else if let Some(n) = t.strip_suffix("cm") { parse_mixed_number(n)? / 30.48 }
+    else if let Some(n) = t.strip_suffix('m') { parse_mixed_number(n)? / 0.3048 }
+    else if let Some((ft, rest)) = t.split_once('\'') {
+      let feet = parse_mixed_number(ft)?;
+      let inches = rest.trim().trim_start_matches('-').trim().trim_end_matches('"').trim();
+      feet + if inches.is_empty() { 0.0 } else { parse_mixed_number(inches)? / 12.0 }
+    }
+    else if let Some(n) = t.strip_suffix('"') { parse_mixed_number(n)? / 12.0 }
+    else { return None };
+  (v > 0.0).then_some(v)
+}
+
+// a printed dimension matched to the line it labels
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
+pub struct DimensionCheck {
+  pub text: String,
+  pub printed_ft: f64,
+  pub measured_ft: f64,
+  // (measured - printed) / printed, in percent
+  pub error_pct: f64,
+  pub line: crate::raster::PseudoLine,
+}
+
+// printed dimensions that disagree with the measured line by more than this suggest a wrong scale
+pub const DIMENSION_TOLERANCE_PCT: f64 = 5.0;
+
+// each dimension text goes to the nearest line whose midpoint is within 1.5 text lengths of it
+pub fn check_dimensions(runs: &[crate::ocr::TextRun], lines: &[crate::raster::PseudoLine], units_per_pixel: f64) -> Vec<DimensionCheck> {
+  let mut out = Vec::new();
+  for r in runs {
+    let Some(printed) = parse_dimension(&r.text) else { continue };
+    let (cx, cy) = r.center();
+    let reach = r.w.max(r.h) * 1.5;
+    let nearest = lines.iter()
+      .map(|l| (l, ((l.x0 + l.x1) * 0.5 - cx).hypot((l.y0 + l.y1) * 0.5 - cy)))
+      .filter(|(_, d)| *d <= reach)
+      .min_by(|a, b| a.1.total_cmp(&b.1));
+    let Some((line, _)) = nearest else { continue };
+    let px = ((line.x1 - line.x0) as f64).hypot((line.y1 - line.y0) as f64);
+    if px <= 0.0 { continue; }
+    let measured = px * units_per_pixel;
+    out.push(DimensionCheck { text: r.text.clone(), printed_ft: printed, measured_ft: measured, error_pct: (measured - printed) / printed * 100.0, line: *line });
+  }
+  out
+}
+
+// units per pixel the printed dimensions imply: the median, so a few mismatched labels don't skew it
+pub fn scale_from_dimensions(checks: &[DimensionCheck], units_per_pixel: f64) -> Option<f64> {
+  let mut implied: Vec<f64> = checks.iter().filter(|c| c.measured_ft > 0.0).map(|c| units_per_pixel * c.printed_ft / c.measured_ft).collect();
+  if implied.is_empty() { return None; }
+  implied.sort_by(f64::total_cmp);
+  Some(implied[implied.len() / 2])
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
+pub struct ScaleGuess {
+  pub units: String,
+  pub units_per_pixel: f64,
+  // how it was found, e.g. "bar"
+  pub source: String,
+}
+
+// "10", "10ft", "10'", "20 m" -> (value, unit if printed)
+fn tick_label(text: &str) -> Option<(f64, Option<&'static str>)> {
+  let t = text.trim().to_lowercase();
+  let (num, unit) = if let Some(n) = t.strip_suffix("ft").or_else(|| t.strip_suffix('\'')) { (n, Some("ft")) }
+    else if let Some(n) = t.strip_suffix('m') { (n, Some("m")) }
+    else { (t.as_str(), None) };
+  let v = num.trim().parse::<f64>().ok().filter(|v| v.is_finite() && *v >= 0.0)?;
+  Some((v, unit))
+}
+
+// graphic scale read off the sheet: the bar from the image, tick numbers from OCR runs just above or
+// below it. Two or more ticks are fit by least squares (pixel x against value); a single tick is
+// taken as the bar's far end with zero at its start.
+pub fn infer_scale_from_bar(gray: &image::GrayImage, runs: &[crate::ocr::TextRun]) -> Option<ScaleGuess> {
+  let bar = crate::raster::detect_bar_scale(gray)?;
+  let mut ticks: Vec<(f64, f64)> = Vec::new();
+  let mut units = None;
+  for r in runs {
+    let (cx, cy) = r.center();
+    if (cy - bar.y).abs() > r.h * 3.0 || cx < bar.x0 - r.w || cx > bar.x1 + r.w { continue; }
+    // unit words printed on their own ("0 5 10 FEET") still count
+    let lower = r.text.to_lowercase();
+    if ["ft", "feet", "foot"].contains(&lower.trim()) { units = units.or(Some("ft")); continue; }
+    if ["m", "meters", "metres"].contains(&lower.trim()) { units = units.or(Some("m")); continue; }
+    for word in r.text.split_whitespace() {
+      let Some((v, u)) = tick_label(word) else { continue };
+      units = units.or(u);
+      ticks.push((cx as f64, v));
+    }
+  }
+  let units = units.unwrap_or("ft").to_string();
+  let upp = match ticks.len() {
+    0 => return None,
+    1 => ticks[0].1 / (bar.x1 - bar.x0) as f64,
+    n => {
+      let n = n as f64;
+      let (mx, mv) = (ticks.iter().map(|t| t.0).sum::<f64>() / n, ticks.iter().map(|t| t.1).sum::<f64>() / n);
+      let sxx: f64 = ticks.iter().map(|t| (t.0 - mx).powi(2)).sum();
+      let sxv: f64 = ticks.iter().map(|t| (t.0 - mx) * (t.1 - mv)).sum();
+      if sxx <= 0.0 { return None; }
+      sxv / sxx
+    }
+  };
+  (upp.is_finite() && upp > 0.0).then(|| ScaleGuess { units, units_per_pixel: upp, source: "bar".into() })
+}
+
+// a line the user knows the length of, in page pixels
+#[derive(Clone, serde::Deserialize, Debug)]
+pub struct KnownLength { pub x0: f32, pub y0: f32, pub x1: f32, pub y1: f32, pub expected_len: f64, pub unit: String }
+
+#[derive(Clone, serde::Serialize, Debug)]
+pub struct ScaleCheck { pub error_pct: f64, pub flagged: bool }
+
+fn feet_per(unit: &str) -> Option<f64> {
+  match unit.trim().to_lowercase().as_str() {
+    "ft" | "feet" | "foot" | "'" => Some(1.0),
+    "in" | "inch" | "inches" | "\"" => Some(1.0 / 12.0),
+    "yd" => Some(3.0),
+    "m" => Some(1.0 / 0.3048),
+    "cm" => Some(1.0 / 30.48),
+    "mm" => Some(1.0 / 304.8),
+    _ => None,
+  }
+}
 
+// percent error of each known length under `units_per_pixel` (feet per pixel); flagged past `tolerance_pct`
+#[tauri::command]
+pub fn verify_scale(units_per_pixel: f64, checks: Vec<KnownLength>, tolerance_pct: Option<f64>) -> Result<Vec<ScaleCheck>, String> {
+  if !units_per_pixel.is_finite() || units_per_pixel <= 0.0 { return Err(format!("invalid_scale: units_per_pixel must be positive, got {units_per_pixel}")); }
+  let tol = tolerance_pct.unwrap_or(DIMENSION_TOLERANCE_PCT);
+  checks.iter().enumerate().map(|(i, c)| {
+    let per = feet_per(&c.unit).ok_or_else(|| format!("invalid_unit: check {i} has unknown unit `{}`", c.unit))?;
+    let expected = c.expected_len * per;
+    if !expected.is_finite() || expected <= 0.0 { return Err(format!("invalid_length: check {i} expected length must be positive")); }
+    let measured = ((c.x1 - c.x0) as f64).hypot((c.y1 - c.y0) as f64) * units_per_pixel;
+    let error_pct = (measured - expected) / expected * 100.0;
+    Ok(ScaleCheck { error_pct, flagged: error_pct.abs() > tol })
+  }).collect()
+}
+
+// user-set scales by (pdf path, zero-based page), in units per pixel at pdf::DEFAULT_DPI; these beat
+// whatever a takeoff infers for that page. Held for the session, like the legend region.
+static PAGE_SCALES: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::BTreeMap<(String, u32), f64>>> = once_cell::sync::Lazy::new(Default::default);
+
+// one document's overrides, converted to pixels at `dpi`
+pub fn page_scale_overrides(pdf_path: &str, dpi: u32) -> std::collections::BTreeMap<u32, f32> {
+  let k = crate::pdf::DEFAULT_DPI as f64 / dpi.max(1) as f64;
+  PAGE_SCALES.lock().iter().filter(|((p, _), _)| p == pdf_path).map(|((_, page), &upp)| (*page, (upp * k) as f32)).collect()
+}
+
+// where a page's scale in the result came from
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
+pub struct PageScale {
+  pub units_per_pixel: f32,
+  // "override", "text", "bar", or "document" when the page had none and took the set's
+  pub source: String,
+}
+
+// `dpi` is the render the scale was measured on (default pdf::DEFAULT_DPI); no value clears the override
+#[tauri::command]
+pub fn set_page_scale(pdf_path: String, page: u32, units_per_pixel: Option<f64>, dpi: Option<u32>) -> Result<(), String> {
+  let Some(upp) = units_per_pixel else { PAGE_SCALES.lock().remove(&(pdf_path, page)); return Ok(()) };
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  if !upp.is_finite() || upp <= 0.0 { return Err(format!("invalid_scale: units_per_pixel must be positive, got {upp}")); }
+  PAGE_SCALES.lock().insert((pdf_path, page), upp * dpi as f64 / crate::pdf::DEFAULT_DPI as f64);
+  Ok(())
+}
+
+// most recently confirmed first, at most RECENT_SCALES; units per pixel at pdf::DEFAULT_DPI
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct RecentScale {
+  pub units_per_pixel: f64,
+  // "auto", "bar" or "manual"
+  pub source: String,
+}
+
+pub const RECENT_SCALES: usize = 8;
+const RECENT_SOURCES: [&str; 3] = ["auto", "bar", "manual"];
+
+// by pdf path, and on disk next to the PDF so the list comes back with the project
+static RECENT: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashMap<String, Vec<RecentScale>>>> = once_cell::sync::Lazy::new(Default::default);
+
+fn recent_sidecar(pdf_path: &str) -> std::path::PathBuf { std::path::PathBuf::from(format!("{pdf_path}.scales.json")) }
+
+fn recent_list(pdf_path: &str) -> Vec<RecentScale> {
+  RECENT.lock().entry(pdf_path.to_string()).or_insert_with(|| {
+    std::fs::read_to_string(recent_sidecar(pdf_path)).ok().and_then(|t| serde_json::from_str(&t).ok()).unwrap_or_default()
+  }).clone()
+}
+
+fn store_recent(pdf_path: &str, list: Vec<RecentScale>) {
+  if let Ok(text) = serde_json::to_string(&list) {
+    // best effort, as with the OCR sidecar
+    if let Err(e) = std::fs::write(recent_sidecar(pdf_path), text) { log::warn!("saving recent scales for {pdf_path}: {e}"); }
+  }
+  RECENT.lock().insert(pdf_path.to_string(), list);
+}
+
+// moves `entry` to the front; the same scale confirmed again (to a part in a million) replaces the old entry
+fn push_recent(pdf_path: &str, entry: RecentScale) {
+  let mut list = recent_list(pdf_path);
+  list.retain(|r| ((r.units_per_pixel - entry.units_per_pixel) / entry.units_per_pixel).abs() > 1e-6);
+  list.insert(0, entry);
+  list.truncate(RECENT_SCALES);
+  store_recent(pdf_path, list);
+}
+
+// records a scale the user accepted; `dpi` is the render it was measured on (default pdf::DEFAULT_DPI)
+#[tauri::command]
+pub fn confirm_scale(pdf_path: String, units_per_pixel: f64, dpi: Option<u32>, source: String) -> Result<Vec<RecentScale>, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  if !units_per_pixel.is_finite() || units_per_pixel <= 0.0 { return Err(format!("invalid_scale: units_per_pixel must be positive, got {units_per_pixel}")); }
+  if !RECENT_SOURCES.contains(&source.as_str()) { return Err(format!("invalid_source: expected one of {RECENT_SOURCES:?}, got `{source}`")); }
+  push_recent(&pdf_path, RecentScale { units_per_pixel: units_per_pixel * dpi as f64 / crate::pdf::DEFAULT_DPI as f64, source });
+  Ok(recent_list(&pdf_path))
+}
+
+#[tauri::command]
+pub fn recent_scales(pdf_path: String) -> Vec<RecentScale> { recent_list(&pdf_path) }
+
+// sets entry `index` of the document's list as the override for its `page` (see set_page_scale; other
+// documents' pages are untouched) and moves it to the front
+#[tauri::command]
+pub fn apply_recent_scale(pdf_path: String, index: usize, page: u32) -> Result<RecentScale, String> {
+  let list = recent_list(&pdf_path);
+  let entry = list.get(index).cloned().ok_or_else(|| format!("invalid_index: {index} out of range ({} recent scales)", list.len()))?;
+  PAGE_SCALES.lock().insert((pdf_path.clone(), page), entry.units_per_pixel);
+  push_recent(&pdf_path, entry.clone());
+  Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn times_are_not_ratios() {
+    assert!(match_scale_text("Meeting at 1:30", 150).is_none());
+    let m = match_scale_text("1:48", 150).expect("plain ratio");
+    assert_eq!(m.matched, "1:48");
+    assert!((m.units_per_pixel - 48.0 * 0.0254 / 150.0).abs() < 1e-7);
+    // a scale word overrides the time reading
+    assert!(match_scale_text("SCALE at 1:30", 150).is_some());
+  }
+
+  #[test]
+  fn imperial_scales_with_feet_and_inches() {
+    let m = match_scale_text("3/4\"=1'-0\"", 150).expect("3/4\" = 1'-0\"");
+    assert_eq!(m.matched, "3/4\"=1'-0\"");
+    assert!((m.units_per_pixel - 1.0 / 0.75 / 150.0).abs() < 1e-7);
+    let m = match_scale_text("1/4\"=1'-6\"", 150).expect("1/4\" = 1'-6\"");
+    assert!((m.units_per_pixel - 1.5 / 0.25 / 150.0).abs() < 1e-7);
+    assert_eq!(parse_feet_inches("1'-6\""), Some(1.5));
+    assert_eq!(parse_feet_inches("1 ft 6 in"), Some(1.5));
+  }
+
+  #[test]
+  fn malformed_feet_inches_are_rejected() {
+    assert_eq!(parse_feet_inches("1'-"), None);
+    assert_eq!(parse_feet_inches("'-6\""), None);
+    assert_eq!(parse_feet_inches("1/0\""), None);
+    assert!(match_scale_text("1/0\"=1'", 150).is_none());
+  }
+
+  #[test]
+  fn page_overrides_stay_with_their_document() {
+    set_page_scale("a.pdf".into(), 3, Some(0.5), None).unwrap();
+    assert_eq!(page_scale_overrides("a.pdf", crate::pdf::DEFAULT_DPI).get(&3), Some(&0.5));
+    assert!(page_scale_overrides("b.pdf", crate::pdf::DEFAULT_DPI).is_empty());
+    set_page_scale("a.pdf".into(), 3, None, None).unwrap();
+    assert!(page_scale_overrides("a.pdf", crate::pdf::DEFAULT_DPI).is_empty());
+  }
+
+  #[test]
+  fn recent_scale_applies_to_its_own_document() {
+    let dir = std::env::temp_dir();
+    let (a, b) = (dir.join(format!("betterbeam-recent-a-{}.pdf", std::process::id())), dir.join(format!("betterbeam-recent-b-{}.pdf", std::process::id())));
+    let (a, b) = (a.to_string_lossy().to_string(), b.to_string_lossy().to_string());
+    confirm_scale(a.clone(), 0.25, None, "manual".into()).unwrap();
+    let applied = apply_recent_scale(a.clone(), 0, 7);
+    let _ = std::fs::remove_file(recent_sidecar(&a));
+    assert_eq!(applied.unwrap().units_per_pixel, 0.25);
+    assert_eq!(page_scale_overrides(&a, crate::pdf::DEFAULT_DPI).get(&7), Some(&0.25));
+    assert!(page_scale_overrides(&b, crate::pdf::DEFAULT_DPI).get(&7).is_none());
+    assert!(apply_recent_scale(b, 0, 7).is_err());
+  }
+
+  #[test]
+  fn quarter_inch_scale_is_feet_per_pixel_at_the_render_dpi() {
+    // a quarter inch of paper is a foot, so an inch is 4 ft and a pixel at 150 dpi is 4/150 ft
+    let m = match_scale_text("SCALE: 1/4\" = 1'-0\"", 150).expect("1/4\" = 1'-0\"");
+    assert_eq!(m.units, "ft");
+    assert!((m.units_per_pixel - 4.0 / 150.0).abs() < 1e-7);
+    // the same sheet rendered at twice the dpi has pixels half the size
+    let hi = match_scale_text("SCALE: 1/4\" = 1'-0\"", 300).unwrap();
+    assert!((hi.units_per_pixel - 4.0 / 300.0).abs() < 1e-7);
+  }
+}
@@ -0,0 +1,108 @@
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::Manager;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme { #[default] Light, Dark }
+
+// persisted user preferences; unknown/missing fields fall back to defaults so old files keep loading
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Settings {
+  pub theme: Theme,
+  pub tile_cache_mb: usize,
+  // extra attempts for fallible job stages (open, render, OCR)
+  pub stage_retries: u32,
+  pub pricing: crate::map::Pricing,
+  // overlay colour per detection label, kept stable across sessions
+  pub label_styles: std::collections::BTreeMap<String, crate::overlay::LabelStyle>,
+  // trace/debug/info/warn/error
+  pub log_level: String,
+  // strip directories from paths in exported error reports
+  pub privacy_mode: bool,
+  // when set, finished takeoffs are written here automatically (created if missing)
+  pub output_dir: Option<String>,
+  // pages of one takeoff processed at once; 0 = one per core
+  pub page_concurrency: usize,
+  // takeoffs running at once; the rest wait in the queue
+  pub max_jobs: usize,
+  // count only the first of pages that fingerprint as the same sheet
+  pub skip_duplicate_pages: bool,
+  // report the score-weighted expected count next to each raw symbol count
+  pub weighted_counts: bool,
+  // look for round symbols (columns, manholes) during takeoff; off by default, it's the slowest pass
+  pub circle_detection: bool,
+  // symbol model in use; None is the bundled one
+  pub model_path: Option<String>,
+  // detector params per model path
+  pub model_params: std::collections::BTreeMap<String, crate::detect::DetectParams>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings { theme: Theme::default(), tile_cache_mb: 256, stage_retries: 2, pricing: Default::default(), label_styles: Default::default(), log_level: "info".into(), privacy_mode: false, output_dir: None, page_concurrency: 0, max_jobs: 2, skip_duplicate_pages: true, weighted_counts: false, circle_detection: false, model_path: None, model_params: Default::default() }
+  }
+}
+
+static PATH: OnceCell<PathBuf> = OnceCell::new();
+static SETTINGS: Lazy<Mutex<Settings>> = Lazy::new(|| Mutex::new(Settings::default()));
+
+pub fn init(app: &tauri::AppHandle) {
+  let Ok(dir) = app.path().app_config_dir() else { return };
+  let path = dir.join("settings.json");
+  if let Ok(text) = std::fs::read_to_string(&path) {
+    match serde_json::from_str::<Settings>(&text) {
+      Ok(s) => *SETTINGS.lock() = s,
+      Err(e) => log::warn!("ignoring unreadable settings {}: {e}", path.display()),
+    }
+  }
+  let _ = PATH.set(path);
+}
+
+pub fn get() -> Settings { SETTINGS.lock().clone() }
+
+pub fn update<F: FnOnce(&mut Settings)>(f: F) -> Result<Settings, String> {
+  let mut s = SETTINGS.lock();
+  f(&mut s);
+  if let Some(path) = PATH.get() {
+    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir).map_err(|e| e.to_string())?; }
+    let text = serde_json::to_string_pretty(&*s).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| e.to_string())?;
+  }
+  Ok(s.clone())
+}
+
+#[tauri::command]
+pub fn get_settings() -> Settings { get() }
+
+// empty clears it
+#[tauri::command]
+pub fn set_output_dir(dir: String) -> Result<Settings, String> {
+  let dir = dir.trim().to_string();
+  if !dir.is_empty() { std::fs::create_dir_all(&dir).map_err(|e| format!("{dir}: {e}"))?; }
+  update(|s| s.output_dir = if dir.is_empty() { None } else { Some(dir) })
+}
+
+pub fn parse_level(level: &str) -> Result<log::LevelFilter, String> {
+  match level.trim().to_ascii_lowercase().as_str() {
+    "trace" => Ok(log::LevelFilter::Trace),
+    "debug" => Ok(log::LevelFilter::Debug),
+    "info" => Ok(log::LevelFilter::Info),
+    "warn" => Ok(log::LevelFilter::Warn),
+    "error" => Ok(log::LevelFilter::Error),
+    other => Err(format!("unknown log level `{other}` (expected trace, debug, info, warn or error)")),
+  }
+}
+
+// the log plugin is built at Trace, so the global max level is the only filter and can move at runtime
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<(), String> {
+  let filter = parse_level(&level)?;
+  log::set_max_level(filter);
+  update(|s| s.log_level = filter.to_string().to_lowercase())?;
+  log::info!("log level set to {filter}");
+  Ok(())
+}
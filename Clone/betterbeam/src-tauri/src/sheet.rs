@@ -0,0 +1,82 @@
+use crate::ocr::TextRun;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SheetId {
+  // as printed, uppercased: "A-101", "E2.1"
+  pub number: String,
+  pub discipline: char,
+}
+
+impl SheetId {
+  // safe for file names: "A-101" stays, "E2.1" becomes "E2_1"
+  pub fn file_stem(&self) -> String {
+    self.number.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+  }
+}
+
+// one or two discipline letters, an optional dash/dot, then digit groups joined by dots or dashes
+fn parse_token(tok: &str) -> Option<SheetId> {
+  let t = tok.trim_matches(|c: char| !c.is_ascii_alphanumeric()).to_ascii_uppercase();
+  let letters = t.chars().take_while(|c| c.is_ascii_uppercase()).count();
+  if !(1..=2).contains(&letters) { return None; }
+  let rest = t[letters..].strip_prefix(['-', '.']).unwrap_or(&t[letters..]);
+  if !rest.starts_with(|c: char| c.is_ascii_digit()) || rest.ends_with(['-', '.']) { return None; }
+  let mut prev_sep = false;
+  let mut digits = 0;
+  for c in rest.chars() {
+    match c {
+      '0'..='9' => { digits += 1; prev_sep = false; }
+      '.' | '-' if !prev_sep => prev_sep = true,
+      _ => return None,
+    }
+  }
+  if digits > 5 { return None; }
+  Some(SheetId { number: t.clone(), discipline: t.chars().next()? })
+}
+
+// prefers a number following a "SHEET" label, otherwise the first plausible token
+pub fn parse_sheet_number(text: &str) -> Option<SheetId> {
+  let toks: Vec<&str> = text.split_whitespace().collect();
+  let labeled = toks.iter().position(|t| t.to_ascii_uppercase().starts_with("SHEET"))
+    .and_then(|i| toks[i + 1..].iter().take(3).find_map(|t| parse_token(t)));
+  labeled.or_else(|| toks.iter().find_map(|t| parse_token(t)))
+}
+
+// text from the usual title-block strip along the right or bottom edge, reading order
+pub fn title_block_text(runs: &[TextRun], page_w: f32, page_h: f32) -> String {
+  let mut inside: Vec<&TextRun> = runs.iter().filter(|r| {
+    let (cx, cy) = r.center();
+    cx >= page_w * 0.8 || cy >= page_h * 0.85
+  }).collect();
+  inside.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+  inside.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+// sheet number from the page's text layer title block; None for scans and pages without one
+pub fn page_sheet_id(doc: &pdfium_render::prelude::PdfDocument, page: u32) -> Option<SheetId> {
+  // 72 dpi: pixels are points, nothing is rendered
+  let (w, h) = crate::transform::Transform::for_page(doc, page, 72).ok()?.pixel_size();
+  let runs = crate::ocr::text_layer_of(doc, page, 72).ok()?;
+  parse_sheet_number(&title_block_text(&runs, w, h))
+}
+
+// pages whose sheet number can't be read
+pub const UNKNOWN_DISCIPLINE: char = '?';
+
+// pages per discipline letter, e.g. {'A': 12, 'E': 8, 'M': 5, '?': 2}
+pub fn discipline_counts(pdf_path: &str) -> Result<std::collections::BTreeMap<char, u32>, String> {
+  let lib = crate::pdf::bind()?;
+  let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| crate::pdf::open_error(pdf_path, e))?;
+  let mut counts = std::collections::BTreeMap::new();
+  for page in 0..doc.pages().len() as u32 {
+    let d = page_sheet_id(&doc, page).map(|s| s.discipline).unwrap_or(UNKNOWN_DISCIPLINE);
+    *counts.entry(d).or_insert(0) += 1;
+  }
+  Ok(counts)
+}
+
+#[tauri::command]
+pub async fn count_pages_by_discipline(pdf_path: String) -> Result<std::collections::BTreeMap<char, u32>, String> {
+  tauri::async_runtime::spawn_blocking(move || discipline_counts(&pdf_path)).await.map_err(|e| e.to_string())?
+}
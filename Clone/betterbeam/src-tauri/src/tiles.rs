@@ -0,0 +1,175 @@
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct TileKey { pub pdf_path: String, pub page: u32, pub tx: i32, pub ty: i32, pub dpi: u32, pub flags: crate::pdf::RenderFlags }
+
+// PNG-encoded tiles, bounded by total encoded bytes rather than entry count
+pub struct TileCache { lru: LruCache<TileKey, Vec<u8>>, bytes: usize, budget: usize }
+
+impl TileCache {
+  fn new(budget: usize) -> Self { TileCache { lru: LruCache::unbounded(), bytes: 0, budget } }
+
+  pub fn get(&mut self, k: &TileKey) -> Option<Vec<u8>> { self.lru.get(k).cloned() }
+
+  pub fn put(&mut self, k: TileKey, png: Vec<u8>) {
+    if png.len() > self.budget { return; }
+    self.bytes += png.len();
+    if let Some(old) = self.lru.put(k, png) { self.bytes -= old.len(); }
+    self.trim();
+  }
+
+  pub fn evict(&mut self, pdf_path: Option<&str>) {
+    match pdf_path {
+      None => { self.lru.clear(); self.bytes = 0; }
+      Some(p) => {
+        let keys: Vec<TileKey> = self.lru.iter().filter(|(k, _)| k.pdf_path == p).map(|(k, _)| k.clone()).collect();
+        for k in keys { if let Some(v) = self.lru.pop(&k) { self.bytes -= v.len(); } }
+      }
+    }
+  }
+
+  pub fn set_budget(&mut self, budget: usize) { self.budget = budget; self.trim(); }
+
+  fn trim(&mut self) {
+    while self.bytes > self.budget {
+      match self.lru.pop_lru() { Some((_, v)) => self.bytes -= v.len(), None => break }
+    }
+  }
+}
+
+pub static TILES: Lazy<Mutex<TileCache>> = Lazy::new(|| Mutex::new(TileCache::new(crate::settings::get().tile_cache_mb * 1024 * 1024)));
+
+#[tauri::command]
+pub fn get_tile(pdf_path: String, page: u32, tx: i32, ty: i32, dpi: u32, flags: Option<crate::pdf::RenderFlags>) -> Option<String> {
+  TILES.lock().get(&TileKey { pdf_path, page, tx, ty, dpi, flags: flags.unwrap_or_default() }).map(|png| BASE64.encode(png))
+}
+
+#[tauri::command]
+pub fn evict_tiles(pdf_path: Option<String>) {
+  TILES.lock().evict(pdf_path.as_deref());
+}
+
+#[tauri::command]
+pub fn set_tile_cache_budget(mb: usize) -> Result<(), String> {
+  crate::settings::update(|s| s.tile_cache_mb = mb)?;
+  TILES.lock().set_budget(mb * 1024 * 1024);
+  Ok(())
+}
+
+// bumped by every prefetch of a page; a prefetch loop that sees a newer value is stale and stops
+static GENERATIONS: Lazy<Mutex<HashMap<(String, u32), Arc<AtomicU64>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn next_generation(pdf_path: &str, page: u32) -> (Arc<AtomicU64>, u64) {
+  let counter = GENERATIONS.lock().entry((pdf_path.to_string(), page)).or_default().clone();
+  let gen = counter.fetch_add(1, Ordering::SeqCst) + 1;
+  (counter, gen)
+}
+
+// region in pixels at `dpi` must be ordered, finite, and on the page
+fn check_region(pdf_path: &str, page: u32, dpi: u32, x0: f32, y0: f32, x1: f32, y1: f32, tile: f32) -> Result<(), String> {
+  crate::pdf::check_dpi(dpi)?;
+  if ![x0, y0, x1, y1, tile].iter().all(|v| v.is_finite()) { return Err("region and tile must be finite".into()); }
+  if x1 < x0 || y1 < y0 { return Err(format!("inverted region ({x0},{y0})-({x1},{y1})")); }
+  if tile <= 0.0 { return Err(format!("tile size must be positive, got {tile}")); }
+  let lib = crate::pdf::bind()?;
+  let doc = lib.load_pdf_from_file(pdf_path, None).map_err(|e| e.to_string())?;
+  let (pw, ph) = crate::transform::Transform::for_page(&doc, page, dpi)?.pixel_size();
+  // allow a pixel of rounding slop at the far edges
+  if x0 < 0.0 || y0 < 0.0 || x1 > pw + 1.0 || y1 > ph + 1.0 {
+    return Err(format!("region ({x0},{y0})-({x1},{y1}) outside page bounds {pw:.0}x{ph:.0} at {dpi} dpi"));
+  }
+  Ok(())
+}
+
+// the first pass renders at this fraction of the asked dpi (but never below COARSE_MIN_DPI)
+const COARSE_DIVISOR: u32 = 4;
+const COARSE_MIN_DPI: u32 = 36;
+
+fn encode_png(img: &image::DynamicImage) -> Option<Vec<u8>> {
+  let mut png = Vec::new();
+  img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).ok().map(|_| png)
+}
+
+// region is in pixels of the page rendered at `dpi`; tiles land in TILES keyed by their origin and render flags.
+// Each tile is announced on `tile:ready` twice: `level: "low"` from a coarse render the frontend can
+// stretch over the tile at once, then `level: "high"` at full dpi. A newer prefetch of the page drops
+// whatever refining is left; tiles already cached skip straight to "high".
+#[tauri::command]
+pub async fn prefetch_view(window: tauri::Window, pdf_path:String, page:u32, dpi:u32, x0:f32, y0:f32, x1:f32, y1:f32, tile:f32, flags: Option<crate::pdf::RenderFlags>) -> Result<(), String> {
+  use tauri::Emitter;
+  let flags = flags.unwrap_or_default();
+  // the bounds check opens the PDF; keep it off the IPC thread
+  let path = pdf_path.clone();
+  tauri::async_runtime::spawn_blocking(move || check_region(&path, page, dpi, x0, y0, x1, y1, tile)).await.map_err(|e| e.to_string())??;
+  let (counter, gen) = next_generation(&pdf_path, page);
+  let owner = window.label().to_string();
+  std::thread::spawn(move || {
+    let stale = || counter.load(Ordering::SeqCst) != gen;
+    let emit = |key: &TileKey, w: u32, h: u32, level: &str, png: &[u8]| {
+      let _ = window.emit_to(&owner, "tile:ready", serde_json::json!({
+        "pdf_path": key.pdf_path, "page": key.page, "tx": key.tx, "ty": key.ty, "dpi": key.dpi,
+        "w": w, "h": h, "level": level, "generation": gen, "png": BASE64.encode(png),
+      }));
+    };
+
+    let overlap = 64.0;
+    let tile = tile.max(256.0).min(1024.0);
+    let step = (tile - overlap).max(256.0);
+    let mut todo = Vec::new();
+    let mut ty = y0;
+    while ty < y1 {
+      let mut tx = x0;
+      while tx < x1 {
+        let (tw, th) = (tile.min(x1 - tx).max(0.0) as u32, tile.min(y1 - ty).max(0.0) as u32);
+        if tw >= 1 && th >= 1 { todo.push((TileKey { pdf_path: pdf_path.clone(), page, tx: tx as i32, ty: ty as i32, dpi, flags }, tw, th)); }
+        tx += step;
+      }
+      ty += step;
+    }
+    let cached: Vec<_> = todo.iter().map(|(k, _, _)| TILES.lock().get(k)).collect();
+    for ((k, w, h), png) in todo.iter().zip(&cached) { if let Some(png) = png { emit(k, *w, *h, "high", png); } }
+    todo = todo.into_iter().zip(cached).filter(|(_, c)| c.is_none()).map(|(t, _)| t).collect();
+    if todo.is_empty() { return; }
+
+    let pdfium = match crate::pdf::bind() { Ok(p) => p, Err(_) => return };
+    let doc = match pdfium.load_pdf_from_file(&pdf_path, None) { Ok(d) => d, Err(_) => return };
+    if stale() { return; }
+    let coarse_dpi = (dpi / COARSE_DIVISOR).max(COARSE_MIN_DPI).min(dpi);
+    if coarse_dpi < dpi {
+      // one coarse render of just the tiles' bounding box, cut up
+      let k = coarse_dpi as f32 / dpi as f32;
+      let origin = |key: &TileKey| (key.tx.max(0) as u32, key.ty.max(0) as u32);
+      let (bx0, by0) = todo.iter().fold((u32::MAX, u32::MAX), |m, (key, _, _)| { let o = origin(key); (m.0.min(o.0), m.1.min(o.1)) });
+      let (bx1, by1) = todo.iter().fold((0, 0), |m, (key, w, h)| { let o = origin(key); (m.0.max(o.0 + w), m.1.max(o.1 + h)) });
+      let (cx0, cy0) = ((bx0 as f32 * k) as u32, (by0 as f32 * k) as u32);
+      let rect = (cx0, cy0, (((bx1 as f32 * k).ceil() as u32).max(cx0 + 1) - cx0), (((by1 as f32 * k).ceil() as u32).max(cy0 + 1) - cy0));
+      let Ok(coarse) = crate::pdf::render_region_with(&doc, page, coarse_dpi, &flags, rect) else { return };
+      for (key, w, h) in &todo {
+        if stale() { return; }
+        let (ox, oy) = origin(key);
+        let cw = ((*w as f32 * k).round() as u32).max(1);
+        let ch = ((*h as f32 * k).round() as u32).max(1);
+        let crop = coarse.crop_imm((ox as f32 * k) as u32 - cx0, (oy as f32 * k) as u32 - cy0, cw, ch);
+        if let Some(png) = encode_png(&crop) { emit(key, *w, *h, "low", &png); }
+      }
+    }
+
+    // the refine pass is the expensive one: each tile renders its own rectangle, and the loop
+    // bails as soon as the view has moved on
+    for (key, w, h) in todo {
+      if stale() { return; }
+      let Ok(img) = crate::pdf::render_region_with(&doc, page, dpi, &flags, (key.tx.max(0) as u32, key.ty.max(0) as u32, w, h)) else { return };
+      let Some(png) = encode_png(&img) else { continue };
+      emit(&key, w, h, "high", &png);
+      TILES.lock().put(key, png);
+    }
+  });
+  Ok(())
+}
@@ -0,0 +1,184 @@
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Four spaces:
+//  - page points: PDF user space, 1/72 in, origin at the MediaBox lower-left, y up, unrotated
+//  - pixels: the page rendered at `dpi` with its /Rotate applied, origin top-left, y down
+//  - screen: pixels after the viewer's zoom and pan, in CSS pixels
+//  - device: screen times the monitor's scale factor, what a HiDPI backing bitmap is drawn in
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Transform {
+  pub dpi: u32,
+  // clockwise, one of 0/90/180/270
+  pub rotation: u16,
+  // MediaBox lower-left and unrotated size, in points
+  pub origin: (f32, f32),
+  pub size: (f32, f32),
+  #[serde(default = "one")]
+  pub zoom: f32,
+  #[serde(default)]
+  pub pan: (f32, f32),
+  // monitor scale factor (window.devicePixelRatio); 2.0 on a typical HiDPI display
+  #[serde(default = "one")]
+  pub device_scale: f32,
+}
+
+fn one() -> f32 { 1.0 }
+
+impl Transform {
+  pub fn new(dpi: u32, rotation: u16, origin: (f32, f32), size: (f32, f32)) -> Self {
+    Transform { dpi, rotation: rotation % 360, origin, size, zoom: 1.0, pan: (0.0, 0.0), device_scale: 1.0 }
+  }
+
+  pub fn with_device_scale(self, device_scale: f32) -> Self { Transform { device_scale, ..self } }
+
+  pub fn for_page(doc: &PdfDocument, page: u32, dpi: u32) -> Result<Self, String> {
+    crate::pdf::check_dpi(dpi)?;
+    let n = doc.pages().len() as u32;
+    if page >= n { return Err(format!("page {page} out of range (document has {n} pages)")); }
+    let p = doc.pages().get(page as u16).map_err(|e| e.to_string())?;
+    let rotation = match p.rotation().map_err(|e| e.to_string())? {
+      PdfPageRenderRotation::None => 0,
+      PdfPageRenderRotation::Degrees90 => 90,
+      PdfPageRenderRotation::Degrees180 => 180,
+      PdfPageRenderRotation::Degrees270 => 270,
+    };
+    let media = p.boundaries().media().map_err(|e| e.to_string())?.bounds;
+    Ok(Transform::new(dpi, rotation, (media.left().value, media.bottom().value), (media.width().value, media.height().value)))
+  }
+
+  pub fn scale(&self) -> f32 { self.dpi as f32 / 72.0 }
+
+  // dpi to render at so one bitmap pixel is one device pixel; the bitmap is then drawn at pixel_size()
+  // CSS pixels, and overlay coordinates stay in pixels at `dpi`
+  pub fn render_dpi(&self) -> u32 { (self.dpi as f32 * self.device_scale).round().max(1.0) as u32 }
+
+  // rendered bitmap size; width and height swap on quarter turns
+  pub fn pixel_size(&self) -> (f32, f32) {
+    let (w, h) = (self.size.0 * self.scale(), self.size.1 * self.scale());
+    if self.rotation % 180 == 90 { (h, w) } else { (w, h) }
+  }
+
+  pub fn point_to_pixel(&self, p: (f32, f32)) -> (f32, f32) {
+    let s = self.scale();
+    let (w, h) = (self.size.0 * s, self.size.1 * s);
+    // unrotated pixel position, then the page rotation turns the bitmap clockwise
+    let (u, v) = ((p.0 - self.origin.0) * s, h - (p.1 - self.origin.1) * s);
+    match self.rotation {
+      90 => (h - v, u),
+      180 => (w - u, h - v),
+      270 => (v, w - u),
+      _ => (u, v),
+    }
+  }
+
+  pub fn pixel_to_point(&self, q: (f32, f32)) -> (f32, f32) {
+    let s = self.scale();
+    let (w, h) = (self.size.0 * s, self.size.1 * s);
+    let (u, v) = match self.rotation {
+      90 => (q.1, h - q.0),
+      180 => (w - q.0, h - q.1),
+      270 => (w - q.1, q.0),
+      _ => q,
+    };
+    (u / s + self.origin.0, (h - v) / s + self.origin.1)
+  }
+
+  pub fn pixel_to_screen(&self, q: (f32, f32)) -> (f32, f32) {
+    (q.0 * self.zoom + self.pan.0, q.1 * self.zoom + self.pan.1)
+  }
+
+  pub fn screen_to_pixel(&self, q: (f32, f32)) -> (f32, f32) {
+    ((q.0 - self.pan.0) / self.zoom, (q.1 - self.pan.1) / self.zoom)
+  }
+
+  pub fn pixel_to_device(&self, q: (f32, f32)) -> (f32, f32) {
+    let (x, y) = self.pixel_to_screen(q);
+    (x * self.device_scale, y * self.device_scale)
+  }
+
+  pub fn device_to_pixel(&self, q: (f32, f32)) -> (f32, f32) {
+    self.screen_to_pixel((q.0 / self.device_scale, q.1 / self.device_scale))
+  }
+
+  pub fn point_to_screen(&self, p: (f32, f32)) -> (f32, f32) { self.pixel_to_screen(self.point_to_pixel(p)) }
+  pub fn screen_to_point(&self, q: (f32, f32)) -> (f32, f32) { self.pixel_to_point(self.screen_to_pixel(q)) }
+}
+
+pub const MAX_DEVICE_SCALE: f32 = 4.0;
+
+// None is 1.0; the render at dpi × scale must still be a valid dpi
+pub fn device_scale(dpi: u32, scale: Option<f32>) -> Result<f32, String> {
+  let s = scale.unwrap_or(1.0);
+  if !s.is_finite() || s <= 0.0 || s > MAX_DEVICE_SCALE { return Err(format!("device scale must be in (0, {MAX_DEVICE_SCALE}], got {s}")); }
+  crate::pdf::check_dpi((dpi as f32 * s).round() as u32)?;
+  Ok(s)
+}
+
+// the frontend gets the same parameters so its overlays agree with Rust's math
+#[tauri::command]
+pub async fn page_transform(pdf_path: String, page: u32, dpi: Option<u32>, device_scale: Option<f32>) -> Result<Transform, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  let s = self::device_scale(dpi, device_scale)?;
+  let lib = crate::pdf::bind()?;
+  let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| e.to_string())?;
+  Ok(Transform::for_page(&doc, page, dpi)?.with_device_scale(s))
+}
+
+// scale factor of the monitor the window is on now; `display:scale_changed` follows moves between monitors
+#[tauri::command]
+pub fn display_scale(window: tauri::Window) -> Result<f64, String> {
+  window.scale_factor().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn close(a: (f32, f32), b: (f32, f32)) -> bool { (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3 }
+
+  fn letter(rotation: u16) -> Transform {
+    Transform { zoom: 1.5, pan: (30.0, -12.0), ..Transform::new(144, rotation, (10.0, 20.0), (612.0, 792.0)) }
+  }
+
+  #[test]
+  fn point_pixel_screen_round_trip() {
+    for rotation in [0, 90, 180, 270] {
+      let t = letter(rotation);
+      for p in [(10.0, 20.0), (100.0, 200.0), (622.0, 812.0)] {
+        let q = t.point_to_pixel(p);
+        assert!(close(t.pixel_to_point(q), p), "rotation {rotation}: pixel round trip of {p:?}");
+        assert!(close(t.screen_to_point(t.point_to_screen(p)), p), "rotation {rotation}: screen round trip of {p:?}");
+      }
+    }
+  }
+
+  #[test]
+  fn quarter_turns_put_the_top_left_where_pdfium_does() {
+    // unrotated top-left of the page in points
+    let tl = (10.0, 20.0 + 792.0);
+    let (w, h) = (612.0 * 2.0, 792.0 * 2.0);
+    assert_eq!(letter(90).pixel_size(), (h, w));
+    assert!(close(letter(90).point_to_pixel(tl), (h, 0.0)));
+    assert!(close(letter(270).point_to_pixel(tl), (0.0, w)));
+    assert!(close(letter(0).point_to_pixel(tl), (0.0, 0.0)));
+  }
+
+  #[test]
+  fn device_scale_one_and_two() {
+    let plain = letter(0);
+    let hidpi = plain.with_device_scale(2.0);
+    let q = (120.0, 48.0);
+    assert_eq!(plain.render_dpi(), 144);
+    assert_eq!(hidpi.render_dpi(), 288);
+    assert!(close(plain.pixel_to_device(q), plain.pixel_to_screen(q)));
+    let (sx, sy) = plain.pixel_to_screen(q);
+    assert!(close(hidpi.pixel_to_device(q), (sx * 2.0, sy * 2.0)));
+    // overlay pixels don't move with the monitor
+    assert!(close(hidpi.point_to_pixel((100.0, 200.0)), plain.point_to_pixel((100.0, 200.0))));
+    for t in [plain, hidpi] { assert!(close(t.device_to_pixel(t.pixel_to_device(q)), q)); }
+    assert!(device_scale(600, Some(2.0)).is_ok());
+    assert!(device_scale(1000, Some(2.0)).is_err());
+    assert!(device_scale(150, Some(0.0)).is_err());
+  }
+}
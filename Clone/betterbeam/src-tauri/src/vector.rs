@@ -0,0 +1,406 @@
+use crate::ocr::TextRun;
+use crate::raster::PseudoLine;
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum GridAxis { Vertical, Horizontal }
+
+#[derive(Clone, Serialize, Debug)]
+pub struct GridLine {
+  pub axis: GridAxis,
+  // x for vertical lines, y for horizontal
+  pub pos: f32,
+  pub start: f32,
+  pub end: f32,
+  pub label: Option<String>,
+}
+
+fn len(l: &PseudoLine) -> f32 { ((l.x1 - l.x0).powi(2) + (l.y1 - l.y0).powi(2)).sqrt() }
+
+// grid lines are long, axis-aligned and often dashed, so collinear fragments are pooled by position
+// before checking coverage; labels are short tokens in the bubbles at either end
+pub fn detect_grid(lines: &[PseudoLine], runs: &[TextRun], page_w: f32, page_h: f32) -> Vec<GridLine> {
+  let pos_tol = 3.0;
+  let mut groups: Vec<(GridAxis, f32, f32, f32, f32)> = Vec::new(); // axis, pos, start, end, covered
+  for l in lines {
+    let (dx, dy) = ((l.x1 - l.x0).abs(), (l.y1 - l.y0).abs());
+    let (axis, pos, a, b) = if dx <= dy * 0.02 {
+      (GridAxis::Vertical, (l.x0 + l.x1) * 0.5, l.y0.min(l.y1), l.y0.max(l.y1))
+    } else if dy <= dx * 0.02 {
+      (GridAxis::Horizontal, (l.y0 + l.y1) * 0.5, l.x0.min(l.x1), l.x0.max(l.x1))
+    } else { continue };
+    match groups.iter_mut().find(|g| g.0 == axis && (g.1 - pos).abs() <= pos_tol) {
+      Some(g) => { g.2 = g.2.min(a); g.3 = g.3.max(b); g.4 += len(l); }
+      None => groups.push((axis, pos, a, b, len(l))),
+    }
+  }
+  let is_label = |t: &str| { let t = t.trim(); !t.is_empty() && t.len() <= 3 && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') };
+  let mut out: Vec<GridLine> = groups.into_iter().filter_map(|(axis, pos, start, end, covered)| {
+    let span = if axis == GridAxis::Vertical { page_h } else { page_w };
+    if end - start < span * 0.6 || covered < (end - start) * 0.5 { return None; }
+    let ends = match axis {
+      GridAxis::Vertical => [(pos, start), (pos, end)],
+      GridAxis::Horizontal => [(start, pos), (end, pos)],
+    };
+    let label = runs.iter()
+      .filter(|r| is_label(&r.text))
+      .filter_map(|r| {
+        let c = r.center();
+        let d = ends.iter().map(|e| ((c.0 - e.0).powi(2) + (c.1 - e.1).powi(2)).sqrt()).fold(f32::MAX, f32::min);
+        let reach = r.w.max(r.h) * 2.0 + 24.0;
+        (d <= reach).then_some((d, r.text.trim().to_string()))
+      })
+      .min_by(|a, b| a.0.total_cmp(&b.0))
+      .map(|(_, t)| t);
+    Some(GridLine { axis, pos, start, end, label })
+  }).collect();
+  out.sort_by(|a, b| (a.axis as u8).cmp(&(b.axis as u8)).then(a.pos.total_cmp(&b.pos)));
+  out
+}
+
+// "B-2" style reference to the nearest labeled vertical and horizontal grid lines
+pub fn grid_ref(grid: &[GridLine], p: (f32, f32)) -> Option<String> {
+  let nearest = |axis: GridAxis, v: f32| grid.iter()
+    .filter(|g| g.axis == axis && g.label.is_some())
+    .min_by(|a, b| (a.pos - v).abs().total_cmp(&(b.pos - v).abs()))
+    .and_then(|g| g.label.clone());
+  let (v, h) = (nearest(GridAxis::Vertical, p.0)?, nearest(GridAxis::Horizontal, p.1)?);
+  // letters conventionally run along one axis and numbers along the other; lead with the letter
+  if v.chars().next().is_some_and(|c| c.is_ascii_digit()) { Some(format!("{h}-{v}")) } else { Some(format!("{v}-{h}")) }
+}
+
+// grids per (pdf_path, page, dpi), from the last page_grid run; positions are pixels at that dpi
+static GRIDS: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashMap<(String, u32, u32), Vec<GridLine>>>> =
+  once_cell::sync::Lazy::new(Default::default);
+
+// the page's grid from the traced vectors, labeled from its text layer or cached OCR
+#[tauri::command]
+pub async fn page_grid(pdf_path: String, page: u32, lines: Vec<PseudoLine>, dpi: Option<u32>) -> Result<Vec<GridLine>, String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  crate::pdf::check_dpi(dpi)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let (w, h) = {
+      let lib = crate::pdf::bind()?;
+      let doc = lib.load_pdf_from_file(&pdf_path, None).map_err(|e| crate::pdf::open_error(&pdf_path, e))?;
+      crate::transform::Transform::for_page(&doc, page, dpi)?.pixel_size()
+    };
+    let runs = crate::ocr::page_runs(&pdf_path, page, dpi)?;
+    let grid = detect_grid(&lines, &runs, w, h);
+    GRIDS.lock().insert((pdf_path, page, dpi), grid.clone());
+    Ok(grid)
+  }).await.map_err(|e| e.to_string())?
+}
+
+// "B-2" for a pixel on a page page_grid has run on; None before that or off a labeled grid
+#[tauri::command]
+pub fn grid_reference(pdf_path: String, page: u32, x: f32, y: f32, dpi: Option<u32>) -> Option<String> {
+  let dpi = dpi.unwrap_or(crate::pdf::DEFAULT_DPI);
+  GRIDS.lock().get(&(pdf_path, page, dpi)).and_then(|g| grid_ref(g, (x, y)))
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+  while parent[i] != i { parent[i] = parent[parent[i]]; i = parent[i]; }
+  i
+}
+
+// single-linkage clustering on a hash grid; returns each point's cluster and the cluster centroids
+fn cluster_points(pts: &[(f32, f32)], tol: f32) -> (Vec<usize>, Vec<(f32, f32)>) {
+  let cell = tol.max(1e-3);
+  let key = |p: (f32, f32)| ((p.0 / cell).floor() as i64, (p.1 / cell).floor() as i64);
+  let mut buckets: std::collections::HashMap<(i64, i64), Vec<usize>> = std::collections::HashMap::new();
+  for (i, &p) in pts.iter().enumerate() { buckets.entry(key(p)).or_default().push(i); }
+
+  let mut parent: Vec<usize> = (0..pts.len()).collect();
+  for (i, &p) in pts.iter().enumerate() {
+    let (kx, ky) = key(p);
+    for gy in ky - 1..=ky + 1 {
+      for gx in kx - 1..=kx + 1 {
+        let Some(b) = buckets.get(&(gx, gy)) else { continue };
+        for &j in b {
+          if j <= i { continue; }
+          let q = pts[j];
+          if (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2) <= tol * tol {
+            let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+            if ri != rj { parent[rj] = ri; }
+          }
+        }
+      }
+    }
+  }
+  let mut ids: std::collections::BTreeMap<usize, usize> = std::collections::BTreeMap::new();
+  let mut sums: Vec<(f32, f32, u32)> = Vec::new();
+  let mut labels = Vec::with_capacity(pts.len());
+  for (i, &p) in pts.iter().enumerate() {
+    let root = find(&mut parent, i);
+    let id = *ids.entry(root).or_insert_with(|| { sums.push((0.0, 0.0, 0)); sums.len() - 1 });
+    let e = &mut sums[id];
+    e.0 += p.0; e.1 += p.1; e.2 += 1;
+    labels.push(id);
+  }
+  (labels, sums.into_iter().map(|(x, y, n)| (x / n as f32, y / n as f32)).collect())
+}
+
+// endpoints within `tol` of each other (transitively) collapse to their centroid
+pub fn find_junctions(lines: &[PseudoLine], tol: f32) -> Vec<(f32, f32)> {
+  let pts: Vec<(f32, f32)> = lines.iter().flat_map(|l| [(l.x0, l.y0), (l.x1, l.y1)]).collect();
+  cluster_points(&pts, tol).1
+}
+
+// cut every segment where it crosses another so the graph has a node at each crossing and T.
+// Only segments sharing a grid cell can cross, so each pair is tested once per shared bucket.
+fn split_at_intersections(lines: &[PseudoLine]) -> Vec<PseudoLine> {
+  let mut cuts: Vec<Vec<f32>> = vec![vec![0.0, 1.0]; lines.len()];
+  // padded so a T that stops just short of the wall still shares a cell with it
+  let (_, cells) = bucket_segments(lines, 1.0);
+  let mut tested = std::collections::HashSet::new();
+  for list in cells.values() {
+    for (k, &i) in list.iter().enumerate() {
+      for &j in &list[k + 1..] {
+        if !tested.insert((i, j)) { continue; }
+        let (a, b) = (&lines[i], &lines[j]);
+        let (rx, ry) = (a.x1 - a.x0, a.y1 - a.y0);
+        let (sx, sy) = (b.x1 - b.x0, b.y1 - b.y0);
+        let den = rx * sy - ry * sx;
+        if den.abs() < 1e-9 { continue; }
+        let (qx, qy) = (b.x0 - a.x0, b.y0 - a.y0);
+        let t = (qx * sy - qy * sx) / den;
+        let u = (qx * ry - qy * rx) / den;
+        let eps = 1e-4;
+        if (-eps..=1.0 + eps).contains(&t) && (-eps..=1.0 + eps).contains(&u) {
+          cuts[i].push(t.clamp(0.0, 1.0));
+          cuts[j].push(u.clamp(0.0, 1.0));
+        }
+      }
+    }
+  }
+  let mut out = Vec::new();
+  for (l, mut ts) in lines.iter().zip(cuts) {
+    ts.sort_by(f32::total_cmp);
+    ts.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+    let at = |t: f32| (l.x0 + (l.x1 - l.x0) * t, l.y0 + (l.y1 - l.y0) * t);
+    for w in ts.windows(2) {
+      let (p, q) = (at(w[0]), at(w[1]));
+      out.push(PseudoLine { x0: p.0, y0: p.1, x1: q.0, y1: q.1 });
+    }
+  }
+  out
+}
+
+fn shoelace(poly: &[(f32, f32)]) -> f64 {
+  let n = poly.len();
+  (0..n).map(|i| {
+    let (a, b) = (poly[i], poly[(i + 1) % n]);
+    a.0 as f64 * b.1 as f64 - b.0 as f64 * a.1 as f64
+  }).sum::<f64>() * 0.5
+}
+
+pub fn trace_rooms(lines: &[PseudoLine]) -> Vec<Vec<(f32, f32)>> {
+  trace_rooms_with(lines, 3.0, 400.0)
+}
+
+// faces of the planar graph built from the segments. Every room comes back with positive shoelace
+// area in the input coordinates; the unbounded face (negative) and loops under `min_area` are dropped.
+pub fn trace_rooms_with(lines: &[PseudoLine], tol: f32, min_area: f64) -> Vec<Vec<(f32, f32)>> {
+  let segs = split_at_intersections(lines);
+  let pts: Vec<(f32, f32)> = segs.iter().flat_map(|l| [(l.x0, l.y0), (l.x1, l.y1)]).collect();
+  let (labels, nodes) = cluster_points(&pts, tol);
+
+  let mut adj: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+  for k in 0..segs.len() {
+    let (u, v) = (labels[2 * k], labels[2 * k + 1]);
+    if u == v || adj[u].contains(&v) { continue; }
+    adj[u].push(v);
+    adj[v].push(u);
+  }
+  // dangling strokes can't bound a room; peel them off so they don't spike into faces
+  let mut stack: Vec<usize> = (0..nodes.len()).filter(|&i| adj[i].len() == 1).collect();
+  while let Some(u) = stack.pop() {
+    if adj[u].len() != 1 { continue; }
+    let v = adj[u].pop().unwrap();
+    adj[v].retain(|&x| x != u);
+    if adj[v].len() == 1 { stack.push(v); }
+  }
+  for (u, list) in adj.iter_mut().enumerate() {
+    let c = nodes[u];
+    list.sort_by(|&a, &b| {
+      let ang = |n: usize| (nodes[n].1 - c.1).atan2(nodes[n].0 - c.0);
+      ang(a).total_cmp(&ang(b))
+    });
+  }
+
+  let mut used = std::collections::HashSet::new();
+  let mut rooms = Vec::new();
+  for u0 in 0..nodes.len() {
+    for &v0 in &adj[u0] {
+      if used.contains(&(u0, v0)) { continue; }
+      let (mut u, mut v) = (u0, v0);
+      let mut face = Vec::new();
+      // next half-edge leaves v just clockwise of the edge back to u
+      while used.insert((u, v)) {
+        face.push(nodes[u]);
+        let list = &adj[v];
+        let pos = list.iter().position(|&x| x == u).unwrap();
+        let w = list[(pos + list.len() - 1) % list.len()];
+        u = v;
+        v = w;
+      }
+      if face.len() >= 3 && shoelace(&face) >= min_area { rooms.push(face); }
+    }
+  }
+  rooms
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct Room {
+  pub outline: Vec<(f32, f32)>,
+  pub area_px: f64,
+  // square drawing units; None without a scale
+  pub area: Option<f64>,
+}
+
+// room takeoff: closed faces of the traced walls, with their areas at the page's scale
+#[tauri::command]
+pub async fn room_outlines(lines: Vec<PseudoLine>, units_per_pixel: Option<f64>) -> Result<Vec<Room>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    trace_rooms(&lines).into_iter().map(|outline| {
+      let area_px = shoelace(&outline);
+      Room { outline, area_px, area: units_per_pixel.map(|u| area_px * u * u) }
+    }).collect()
+  }).await.map_err(|e| e.to_string())
+}
+
+// uniform grid over segment bounding boxes; a query only touches the cells its radius covers
+pub struct LineIndex {
+  lines: Vec<PseudoLine>,
+  cell: f32,
+  cells: std::collections::HashMap<(i32, i32), Vec<usize>>,
+}
+
+fn closest_on_segment(l: &PseudoLine, p: (f32, f32)) -> (f32, f32) {
+  let (dx, dy) = (l.x1 - l.x0, l.y1 - l.y0);
+  let len2 = dx * dx + dy * dy;
+  let t = if len2 > 0.0 { (((p.0 - l.x0) * dx + (p.1 - l.y0) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+  (l.x0 + t * dx, l.y0 + t * dy)
+}
+
+// cell size about the median segment length keeps both bucket counts and per-bucket lists small.
+// Each segment goes in every cell its bounding box, grown by `pad`, touches; lists are in index order.
+fn bucket_segments(lines: &[PseudoLine], pad: f32) -> (f32, std::collections::HashMap<(i32, i32), Vec<usize>>) {
+  let mut lengths: Vec<f32> = lines.iter().map(len).filter(|l| *l > 0.0).collect();
+  lengths.sort_by(f32::total_cmp);
+  let cell = lengths.get(lengths.len() / 2).copied().unwrap_or(64.0).clamp(8.0, 512.0);
+  let mut cells: std::collections::HashMap<(i32, i32), Vec<usize>> = Default::default();
+  for (i, l) in lines.iter().enumerate() {
+    let (cx0, cx1) = (((l.x0.min(l.x1) - pad) / cell).floor() as i32, ((l.x0.max(l.x1) + pad) / cell).floor() as i32);
+    let (cy0, cy1) = (((l.y0.min(l.y1) - pad) / cell).floor() as i32, ((l.y0.max(l.y1) + pad) / cell).floor() as i32);
+    for cx in cx0..=cx1 {
+      for cy in cy0..=cy1 { cells.entry((cx, cy)).or_default().push(i); }
+    }
+  }
+  (cell, cells)
+}
+
+pub fn build_line_index(lines: Vec<PseudoLine>) -> LineIndex {
+  let (cell, cells) = bucket_segments(&lines, 0.0);
+  LineIndex { lines, cell, cells }
+}
+
+// nearest point on any segment within `radius` of `p`, anywhere along it rather than only at endpoints
+pub fn nearest_on_lines(index: &LineIndex, p: (f32, f32), radius: f32) -> Option<(f32, f32)> {
+  if radius.is_nan() || radius <= 0.0 { return None; }
+  let c = index.cell;
+  let (cx0, cx1) = (((p.0 - radius) / c).floor() as i32, ((p.0 + radius) / c).floor() as i32);
+  let (cy0, cy1) = (((p.1 - radius) / c).floor() as i32, ((p.1 + radius) / c).floor() as i32);
+  let mut best: Option<((f32, f32), f32)> = None;
+  for cx in cx0..=cx1 {
+    for cy in cy0..=cy1 {
+      for &i in index.cells.get(&(cx, cy)).into_iter().flatten() {
+        let q = closest_on_segment(&index.lines[i], p);
+        let d = (q.0 - p.0).hypot(q.1 - p.1);
+        if d <= radius && best.is_none_or(|(_, bd)| d < bd) { best = Some((q, d)); }
+      }
+    }
+  }
+  best.map(|(q, _)| q)
+}
+
+// one index per app, replaced whenever the traced sheet's vectors change
+static SNAP_INDEX: once_cell::sync::Lazy<parking_lot::Mutex<Option<LineIndex>>> = once_cell::sync::Lazy::new(|| parking_lot::Mutex::new(None));
+
+#[tauri::command]
+pub fn set_snap_lines(lines: Vec<PseudoLine>) -> usize {
+  let n = lines.len();
+  *SNAP_INDEX.lock() = Some(build_line_index(lines));
+  n
+}
+
+// called per mouse move while tracing
+#[tauri::command]
+pub fn snap_to_lines(x: f32, y: f32, radius: f32) -> Option<(f32, f32)> {
+  SNAP_INDEX.lock().as_ref().and_then(|idx| nearest_on_lines(idx, (x, y), radius))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn seg(x0: f32, y0: f32, x1: f32, y1: f32) -> PseudoLine { PseudoLine { x0, y0, x1, y1 } }
+
+  #[test]
+  fn corner_endpoints_collapse_to_one_junction() {
+    // three runs ending a pixel or so apart around (10, 10)
+    let lines = [seg(0.0, 10.0, 10.0, 10.0), seg(10.5, 10.4, 10.5, 30.0), seg(9.6, 9.7, 30.0, 0.0)];
+    let j = find_junctions(&lines, 2.0);
+    let near: Vec<_> = j.iter().filter(|p| (p.0 - 10.0).hypot(p.1 - 10.0) < 2.0).collect();
+    assert_eq!(near.len(), 1);
+    // the far ends stay their own nodes
+    assert_eq!(j.len(), 4);
+  }
+
+  fn run(text: &str, x: f32, y: f32) -> TextRun { TextRun { text: text.into(), x, y, w: 16.0, h: 16.0, conf: 90.0 } }
+
+  #[test]
+  fn labeled_axes_name_the_nearest_bay() {
+    // columns B and C across the top, rows 2 and 3 down the left, bubbles just past the line ends
+    let lines = [
+      seg(100.0, 40.0, 100.0, 960.0), seg(500.0, 40.0, 500.0, 960.0),
+      seg(40.0, 200.0, 960.0, 200.0), seg(40.0, 600.0, 960.0, 600.0),
+      // a short wall is not grid
+      seg(300.0, 300.0, 300.0, 400.0),
+    ];
+    let runs = [run("B", 92.0, 10.0), run("C", 492.0, 10.0), run("2", 8.0, 192.0), run("3", 8.0, 592.0)];
+    let grid = detect_grid(&lines, &runs, 1000.0, 1000.0);
+    assert_eq!(grid.len(), 4);
+    assert!(grid.iter().all(|g| g.label.is_some()));
+    assert_eq!(grid_ref(&grid, (120.0, 250.0)).as_deref(), Some("B-2"));
+    assert_eq!(grid_ref(&grid, (450.0, 580.0)).as_deref(), Some("C-3"));
+  }
+
+  // two 100x100 rooms side by side, in page pixels (y down)
+  fn two_rooms() -> Vec<PseudoLine> {
+    vec![
+      seg(0.0, 0.0, 200.0, 0.0), seg(0.0, 100.0, 200.0, 100.0),
+      seg(0.0, 0.0, 0.0, 100.0), seg(200.0, 0.0, 200.0, 100.0),
+      seg(100.0, 0.0, 100.0, 100.0),
+    ]
+  }
+
+  #[test]
+  fn shared_wall_gives_two_rooms_and_no_outer_face() {
+    let rooms = trace_rooms(&two_rooms());
+    assert_eq!(rooms.len(), 2);
+    for r in &rooms { assert!((shoelace(r) - 10_000.0).abs() < 1.0, "{r:?}"); }
+  }
+
+  #[test]
+  fn dangling_strokes_are_ignored() {
+    let mut lines = vec![seg(0.0, 0.0, 100.0, 0.0), seg(100.0, 0.0, 100.0, 100.0), seg(100.0, 100.0, 0.0, 100.0), seg(0.0, 100.0, 0.0, 0.0)];
+    // one spur into the room off the left wall, one out past the right wall
+    lines.push(seg(0.0, 50.0, 40.0, 50.0));
+    lines.push(seg(100.0, 50.0, 150.0, 50.0));
+    let rooms = trace_rooms(&lines);
+    assert_eq!(rooms.len(), 1);
+    assert!((shoelace(&rooms[0]) - 10_000.0).abs() < 1.0);
+    assert!(rooms[0].iter().all(|p| p.0 <= 100.5));
+  }
+}